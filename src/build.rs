@@ -0,0 +1,198 @@
+//! Helpers for generating manpages from build scripts
+
+use crate::manual::check_page_name;
+use crate::{write_updated, Doc, ManualSet, Section};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Print `cargo:rerun-if-changed` lines for given paths
+///
+/// Once a build script prints any of those lines cargo stops rerunning it when other files in
+/// the package change, so the list should include the build script itself and every file
+/// documentation is generated from.
+pub fn rerun_if_changed<I, P>(paths: I)
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<Path>,
+{
+    for path in paths {
+        println!("cargo:rerun-if-changed={}", path.as_ref().display());
+    }
+}
+
+/// Directory to write generated manpages to, usually from a build script
+///
+/// Files are only written if their contents changed, see [`write_updated`]
+///
+/// ```no_run
+/// // build.rs
+/// use roff::{rerun_if_changed, BuildOutput, Doc, Section};
+///
+/// let mut doc = Doc::default();
+/// doc.section("Description").paragraph("Does things");
+///
+/// rerun_if_changed(["build.rs", "src/cli.rs"]);
+/// let path = BuildOutput::out_dir()
+///     .unwrap()
+///     .write_manpage(&doc, "FOO", Section::General, &[])
+///     .unwrap();
+/// // path is $OUT_DIR/foo.1
+/// ```
+#[derive(Debug, Clone)]
+pub struct BuildOutput {
+    dir: PathBuf,
+    #[cfg(feature = "gzip")]
+    gzip: bool,
+}
+
+impl BuildOutput {
+    /// Write files to a given directory
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Self {
+            dir: dir.as_ref().to_owned(),
+            #[cfg(feature = "gzip")]
+            gzip: false,
+        }
+    }
+
+    /// Write files to `OUT_DIR` set by cargo for build scripts
+    ///
+    /// # Errors
+    /// Reports missing `OUT_DIR` variable, when called outside of a build script
+    pub fn out_dir() -> io::Result<Self> {
+        let dir = std::env::var_os("OUT_DIR").ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "OUT_DIR is not set, this should be called from a build script",
+            )
+        })?;
+        Ok(Self::new(dir))
+    }
+
+    /// Compress files with gzip and add `.gz` to their names
+    ///
+    /// Default is `false`
+    #[cfg(feature = "gzip")]
+    pub fn gzip(&mut self, state: bool) -> &mut Self {
+        self.gzip = state;
+        self
+    }
+
+    /// Directory files are written to
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Write rendered file relative to the output directory, creating subdirectories as needed
+    fn write(&self, path: &Path, contents: &str) -> io::Result<PathBuf> {
+        let path = self.dir.join(path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        #[cfg(feature = "gzip")]
+        {
+            if self.gzip {
+                let mut name = path.into_os_string();
+                name.push(".gz");
+                let path = PathBuf::from(name);
+                write_updated(&path, &compress(contents.as_bytes())?)?;
+                return Ok(path);
+            }
+        }
+        write_updated(&path, contents.as_bytes())?;
+        Ok(path)
+    }
+
+    /// Render a manpage into a file named after its title and section such as `foo.1` and
+    /// return its path
+    ///
+    /// ```rust
+    /// # use ::roff::*;
+    /// let dir = std::env::temp_dir().join(format!("roff-doc-{}", std::process::id()));
+    /// let mut doc = Doc::default();
+    /// doc.section("Description").paragraph("Does things");
+    /// let path = BuildOutput::new(&dir)
+    ///     .write_manpage(&doc, "FOO", Section::General, &[])
+    ///     .unwrap();
+    ///
+    /// assert_eq!(path, dir.join("foo.1"));
+    /// # std::fs::remove_dir_all(&dir).unwrap();
+    /// ```
+    ///
+    /// # Errors
+    /// Reports any file IO errors and titles that can't be used as file names, such as ones
+    /// containing `/`
+    pub fn write_manpage(
+        &self,
+        doc: &Doc,
+        title: &str,
+        section: Section<'_>,
+        extra: &[&str],
+    ) -> io::Result<PathBuf> {
+        let name = title.to_lowercase();
+        check_page_name(&name)?;
+        let name = format!("{}.{}", name, section.as_str());
+        let page = doc.render_to_manpage(title, section, extra);
+        self.write(Path::new(&name), &page)
+    }
+
+    /// Render all the pages of a manual set into `man1` and similar subdirectories and return
+    /// their paths
+    ///
+    /// # Errors
+    /// Reports any file IO errors and page names that can't be used as file names
+    pub fn write_manual(&self, set: &ManualSet<'_>, extra: &[&str]) -> io::Result<Vec<PathBuf>> {
+        set.check_names()?;
+        set.render_to_manpages(extra)
+            .into_iter()
+            .map(|(path, page)| self.write(&path, &page))
+            .collect()
+    }
+}
+
+#[cfg(feature = "gzip")]
+fn compress(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+    // default header has no timestamp or file name so output is reproducible
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(bytes)?;
+    encoder.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use super::BuildOutput;
+    use crate::test_dir::TestDir;
+    use crate::{Doc, Section};
+
+    #[test]
+    fn invalid_titles() {
+        let tmp = TestDir::new("titles");
+        let out = BuildOutput::new(tmp.path().join("out"));
+        for title in &["../../etc/x", "a/b", "..", ""] {
+            let err = out
+                .write_manpage(&Doc::default(), title, Section::General, &[])
+                .unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput, "{}", title);
+        }
+        assert_eq!(std::fs::read_dir(tmp.path()).unwrap().count(), 0);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn gzip_output() {
+        use crate::{text, ManualSet};
+        let tmp = TestDir::new("gzip");
+        let dir = tmp.path();
+        let set = ManualSet::new("foo", Section::General, "do things", text("nothing here"));
+        let paths = BuildOutput::new(dir)
+            .gzip(true)
+            .write_manual(&set, &[])
+            .unwrap();
+        assert_eq!(paths, [dir.join("man1/foo.1.gz")]);
+        let bytes = std::fs::read(&paths[0]).unwrap();
+        assert_eq!(bytes[..2], [0x1f, 0x8b]);
+    }
+}
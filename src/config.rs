@@ -0,0 +1,259 @@
+//! Documentation for configuration file keys, usually found in section 5 manpages
+
+use crate::semantic::{metavar, mono, text, Doc, Write};
+
+/// Documentation for a single configuration key
+///
+/// Renders as a definition list entry so it should go inside of a [`dlist`](Doc::dlist) or a
+/// [`ConfigGroup`]. Keys can contain nested keys, those are rendered as an indented definition
+/// list after the description.
+///
+/// ```rust
+/// # use ::roff::*;
+/// let port = ConfigKey::new("server.port")
+///     .value_type("integer")
+///     .help(text("Port to listen on"))
+///     .default_value("8080")
+///     .since("1.2");
+/// let mut doc = Doc::default();
+/// doc.dlist([port]);
+/// let doc = doc.render_to_markdown();
+/// let expected = "\
+/// <dl>
+/// <dt><tt><b>server.port</b></tt> (<tt><i>integer</i></tt>)</dt>
+/// <dd>Port to listen on<br>Default: <tt>8080</tt><br>Since: 1.2</dd></dl>";
+///
+/// assert_eq!(doc, expected);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ConfigKey {
+    path: String,
+    ty: Option<String>,
+    help: Doc,
+    default: Option<String>,
+    values: Vec<(String, Doc)>,
+    example: Option<(Option<String>, String)>,
+    since: Option<String>,
+    keys: Vec<ConfigKey>,
+}
+
+impl ConfigKey {
+    /// Create documentation for a key, nested keys should use path relative to the parent
+    #[must_use]
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_owned(),
+            ..Self::default()
+        }
+    }
+
+    /// Type of the value such as `string` or `integer`
+    #[must_use]
+    pub fn value_type(mut self, ty: &str) -> Self {
+        self.ty = Some(ty.to_owned());
+        self
+    }
+
+    /// Description of the key, should contain text level fragments
+    #[must_use]
+    pub fn help<S>(mut self, help: S) -> Self
+    where
+        S: Write,
+    {
+        self.help = Doc::default();
+        self.help.push(help);
+        self
+    }
+
+    /// Value used when the key is not present
+    #[must_use]
+    pub fn default_value(mut self, value: &str) -> Self {
+        self.default = Some(value.to_owned());
+        self
+    }
+
+    /// Add one of the accepted values along with its description
+    #[must_use]
+    pub fn allowed_value<S>(mut self, value: &str, description: S) -> Self
+    where
+        S: Write,
+    {
+        let mut doc = Doc::default();
+        doc.push(description);
+        self.values.push((value.to_owned(), doc));
+        self
+    }
+
+    /// Snippet of a configuration file that uses the key, see [`code`](Doc::code)
+    #[must_use]
+    pub fn example(mut self, language: Option<&str>, snippet: &str) -> Self {
+        self.example = Some((language.map(str::to_owned), snippet.to_owned()));
+        self
+    }
+
+    /// Version the key first appeared in
+    #[must_use]
+    pub fn since(mut self, version: &str) -> Self {
+        self.since = Some(version.to_owned());
+        self
+    }
+
+    /// Add a nested key, for keys containing tables or sections
+    #[must_use]
+    pub fn key(mut self, key: ConfigKey) -> Self {
+        self.keys.push(key);
+        self
+    }
+
+    fn write_term(&self, to: &mut Doc) {
+        to.literal(&self.path);
+        if let Some(ty) = &self.ty {
+            to.push([text(" ("), metavar(ty), text(")")]);
+        }
+    }
+
+    fn write_definition(&self, to: &mut Doc) {
+        // everything but help goes on a separate line
+        let mut first = true;
+        let mut separate = |to: &mut Doc| {
+            if !first {
+                to.line_break();
+            }
+            first = false;
+        };
+        if !self.help.is_empty() {
+            separate(to);
+            self.help.write(to);
+        }
+        if let Some(default) = &self.default {
+            separate(to);
+            to.push([text("Default: "), mono(default)]);
+        }
+        if !self.values.is_empty() {
+            separate(to);
+            to.text("Allowed values:");
+            for (value, description) in &self.values {
+                to.line_break().literal(value);
+                if !description.is_empty() {
+                    to.text(": ");
+                    description.write(to);
+                }
+            }
+        }
+        if let Some(since) = &self.since {
+            separate(to);
+            to.text(format!("Since: {}", since));
+        }
+        // blocks are indented to stay inside of the definition in manpages
+        if self.example.is_some() || !self.keys.is_empty() {
+            to.indent(None, |doc: &mut Doc| {
+                if let Some((language, snippet)) = &self.example {
+                    doc.code(language.as_deref(), snippet.as_str());
+                }
+                if !self.keys.is_empty() {
+                    doc.dlist(self.keys.as_slice());
+                }
+            });
+        }
+    }
+}
+
+impl Write for ConfigKey {
+    fn write(&self, to: &mut Doc) {
+        to.definition(
+            |doc: &mut Doc| self.write_term(doc),
+            |doc: &mut Doc| self.write_definition(doc),
+        );
+    }
+}
+
+/// A group of configuration keys documented under a subsection heading
+///
+/// ```rust
+/// # use ::roff::*;
+/// let group = ConfigGroup::new("Server")
+///     .key(ConfigKey::new("host").value_type("string").help(text("Address to bind")));
+/// let mut doc = Doc::default();
+/// doc.push(group);
+/// let doc = doc.render_to_manpage("FOO.TOML", Section::FileFormat, &[]);
+/// assert!(doc.ends_with(
+///     ".SS Server\n.TP\n\\f(CBhost\\fP\\fR (\\fP\\fIstring\\fP\\fR)\\fP\n\\fRAddress to bind\\fP\n.PP\n"
+/// ));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ConfigGroup {
+    name: String,
+    keys: Vec<ConfigKey>,
+}
+
+impl ConfigGroup {
+    /// Create an empty group with a given heading
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            keys: Vec::new(),
+        }
+    }
+
+    /// Add a key to the group
+    #[must_use]
+    pub fn key(mut self, key: ConfigKey) -> Self {
+        self.keys.push(key);
+        self
+    }
+}
+
+impl Write for ConfigGroup {
+    fn write(&self, to: &mut Doc) {
+        to.subsection(&self.name).dlist(self.keys.as_slice());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ConfigGroup, ConfigKey};
+    use crate::semantic::{text, Doc};
+    use crate::Section;
+
+    #[test]
+    fn allowed_values() {
+        let key = ConfigKey::new("log")
+            .help(text("Verbosity"))
+            .allowed_value("quiet", text("errors only"))
+            .allowed_value("loud", Doc::default());
+        let mut doc = Doc::default();
+        doc.dlist([key]);
+        let expected = "<dl>\n<dt><tt><b>log</b></tt></dt>\n<dd>Verbosity<br>Allowed values:\
+                        <br><tt><b>quiet</b></tt>: errors only<br><tt><b>loud</b></tt></dd></dl>";
+        assert_eq!(doc.render_to_markdown(), expected);
+    }
+
+    #[test]
+    fn nested_keys_manpage() {
+        let group = ConfigGroup::new("Server").key(
+            ConfigKey::new("tls")
+                .example(Some("toml"), "[tls]")
+                .key(ConfigKey::new("cert").value_type("path")),
+        );
+        let mut doc = Doc::default();
+        doc.push(group);
+        let page = doc.render_to_manpage("FOO.TOML", Section::FileFormat, &[]);
+        let expected = ".SS Server\n.TP\n\\f(CBtls\\fP\n.RS\n.PP\n.EX\n[tls]\n.EE\n\
+                        .TP\n\\f(CBcert\\fP\\fR (\\fP\\fIpath\\fP\\fR)\\fP\n.PP\n.RE\n.PP\n";
+        assert!(page.ends_with(expected), "{}", page);
+    }
+
+    #[test]
+    fn special_characters() {
+        let key = ConfigKey::new("-a.\\b")
+            .default_value("'quoted'")
+            .since("1.0-rc.1");
+        let mut doc = Doc::default();
+        doc.dlist([key]);
+        let page = doc.render_to_manpage("X", Section::FileFormat, &[]);
+        let expected = ".TP\n\\f(CB\\-a.\\\\b\\fP\n\\fRDefault: \\fP\\f(CR\\*(Aqquoted\\*(Aq\\fP\n\
+                        .br\n\\fRSince: 1.0\\-rc.1\\fP\n.PP\n";
+        assert!(page.ends_with(expected), "{}", page);
+    }
+}
@@ -0,0 +1,210 @@
+//! Unified line diffs to explain changes in generated files
+
+/// Lines of context around each change
+const CONTEXT: usize = 3;
+
+/// Largest table for the longest common subsequence, bigger changes are shown as a whole
+/// replacement to keep memory use in check
+const MAX_TABLE: usize = 1 << 22;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Edit {
+    Keep,
+    Delete,
+    Insert,
+}
+
+/// Shortest sequence of line edits turning `old` into `new`, based on the longest common
+/// subsequence
+fn edits(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    // common prefix and suffix are cheap to find and usually cover most of the file
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    let mut res = vec![Edit::Keep; prefix];
+    if (old_mid.len() + 1).saturating_mul(new_mid.len() + 1) > MAX_TABLE {
+        res.extend(std::iter::repeat(Edit::Delete).take(old_mid.len()));
+        res.extend(std::iter::repeat(Edit::Insert).take(new_mid.len()));
+        res.extend(std::iter::repeat(Edit::Keep).take(suffix));
+        return res;
+    }
+
+    // lcs[i][j] - length of the longest common subsequence of old_mid[i..] and new_mid[j..]
+    let width = new_mid.len() + 1;
+    let mut lcs = vec![0usize; (old_mid.len() + 1) * width];
+    for i in (0..old_mid.len()).rev() {
+        for j in (0..new_mid.len()).rev() {
+            lcs[i * width + j] = if old_mid[i] == new_mid[j] {
+                lcs[(i + 1) * width + j + 1] + 1
+            } else {
+                lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < old_mid.len() || j < new_mid.len() {
+        if i < old_mid.len() && j < new_mid.len() && old_mid[i] == new_mid[j] {
+            res.push(Edit::Keep);
+            i += 1;
+            j += 1;
+        } else if j == new_mid.len()
+            || (i < old_mid.len() && lcs[(i + 1) * width + j] >= lcs[i * width + j + 1])
+        {
+            res.push(Edit::Delete);
+            i += 1;
+        } else {
+            res.push(Edit::Insert);
+            j += 1;
+        }
+    }
+    res.extend(std::iter::repeat(Edit::Keep).take(suffix));
+    res
+}
+
+/// Write a single line of a hunk, marking a missing newline the same way `diff` does
+fn push_line(res: &mut String, mark: char, line: &str) {
+    res.push(mark);
+    res.push_str(line);
+    if !line.ends_with('\n') {
+        res.push_str("\n\\ No newline at end of file\n");
+    }
+}
+
+/// Unified diff between two texts, empty if they are the same
+pub(crate) fn unified(old: &str, new: &str, name: &str) -> String {
+    let old = old.split_inclusive('\n').collect::<Vec<_>>();
+    let new = new.split_inclusive('\n').collect::<Vec<_>>();
+    let edits = edits(&old, &new);
+
+    let mut res = String::new();
+    // position of each edit in old and new texts
+    let mut positions = Vec::with_capacity(edits.len());
+    let (mut o, mut n) = (0, 0);
+    for edit in &edits {
+        positions.push((o, n));
+        match edit {
+            Edit::Keep => {
+                o += 1;
+                n += 1;
+            }
+            Edit::Delete => o += 1,
+            Edit::Insert => n += 1,
+        }
+    }
+
+    let mut ix = 0;
+    while ix < edits.len() {
+        if edits[ix] == Edit::Keep {
+            ix += 1;
+            continue;
+        }
+        // hunk spans changes separated by at most 2 * CONTEXT unchanged lines
+        let start = ix.saturating_sub(CONTEXT);
+        let mut end = ix;
+        let mut kept = 0;
+        while end < edits.len() && kept <= 2 * CONTEXT {
+            if edits[end] == Edit::Keep {
+                kept += 1;
+            } else {
+                kept = 0;
+            }
+            end += 1;
+        }
+        let end = end - kept.saturating_sub(CONTEXT);
+
+        let old_len = edits[start..end]
+            .iter()
+            .filter(|e| **e != Edit::Insert)
+            .count();
+        let new_len = edits[start..end]
+            .iter()
+            .filter(|e| **e != Edit::Delete)
+            .count();
+        let (old_start, new_start) = positions[start];
+        if res.is_empty() {
+            res.push_str(&format!("--- a/{0}\n+++ b/{0}\n", name));
+        }
+        // empty ranges point at the line before them
+        res.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            old_start + usize::from(old_len > 0),
+            old_len,
+            new_start + usize::from(new_len > 0),
+            new_len
+        ));
+        for (edit, (o, n)) in edits[start..end].iter().zip(&positions[start..end]) {
+            match edit {
+                Edit::Keep => push_line(&mut res, ' ', old[*o]),
+                Edit::Delete => push_line(&mut res, '-', old[*o]),
+                Edit::Insert => push_line(&mut res, '+', new[*n]),
+            }
+        }
+        ix = end;
+    }
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use super::unified;
+
+    #[test]
+    fn same() {
+        assert_eq!(unified("a\nb\n", "a\nb\n", "x"), "");
+    }
+
+    #[test]
+    fn hunks() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n";
+        let new = "1\n2\nthree\n4\n5\n6\n7\n8\n9\n10\n11\n";
+        let expected = "\
+--- a/x
++++ b/x
+@@ -1,6 +1,6 @@
+ 1
+ 2
+-3
++three
+ 4
+ 5
+ 6
+@@ -9,4 +9,3 @@
+ 9
+ 10
+ 11
+-12
+";
+        assert_eq!(unified(old, new, "x"), expected);
+    }
+
+    #[test]
+    fn large_change() {
+        let old = (0..3000).map(|i| format!("{}\n", i)).collect::<String>();
+        let new = (0..3000)
+            .map(|i| format!("{}\n", i * 2))
+            .collect::<String>();
+        let diff = unified(&old, &new, "x");
+        assert!(diff.starts_with("--- a/x\n+++ b/x\n@@ -1,3000 +1,3000 @@\n 0\n-1\n"));
+        // the first line is the same, the rest is replaced as a whole
+        let count = |mark: char| diff.lines().skip(3).filter(|l| l.starts_with(mark)).count();
+        assert_eq!((count('-'), count('+')), (2999, 2999));
+    }
+
+    #[test]
+    fn missing_newline() {
+        let expected = "--- a/x\n+++ b/x\n@@ -1,1 +1,1 @@\n-a\n+a\n\\ No newline at end of file\n";
+        assert_eq!(unified("a\n", "a", "x"), expected);
+        assert_eq!(
+            unified("", "a\n", "x"),
+            "--- a/x\n+++ b/x\n@@ -0,0 +1,1 @@\n+a\n"
+        );
+    }
+}
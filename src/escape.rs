@@ -1,10 +1,14 @@
 //! Escape and concatenate string slices according to Roff Escape rules
 
+use crate::roff::Font;
+
 /// Apostrophes handling configuration
 ///
 /// To generate manpages you most likely want to have this in `Handle` state.
-/// See <https://bugs.debian.org/cgi-bin/bugreport.cgi?bug=507673#65> for more details
-#[derive(Eq, PartialEq, Copy, Clone)]
+/// See <https://bugs.debian.org/cgi-bin/bugreport.cgi?bug=507673#65> for more details.
+/// Only [`Dialect::Groff`] and [`Dialect::Mandoc`] have a way to represent an apostrophe,
+/// for other dialects this has no effect
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
 pub enum Apostrophes {
     /// Replace apostrophes with special code that
     Handle,
@@ -12,10 +16,211 @@ pub enum Apostrophes {
     DontHandle,
 }
 
-/// Replacement for apostrophes, if active
+/// Non-ASCII characters handling configuration
+///
+/// Most of the modern formatters accept UTF-8 input, but some of them as well as some locales
+/// need to use roff special characters instead.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Glyphs {
+    /// Write all the characters as is
+    Utf8,
+    /// Replace typographic characters such as dashes, curly quotes, ellipsis or bullets with
+    /// roff special characters (`\(em`, `\(lq`, `\(bu`), write the rest as is
+    Typographic,
+    /// Replace all the non-ASCII characters with roff special characters, by name if they have
+    /// one (`\(co`) or by code point otherwise (`\[u00E9]`), output is pure ASCII. Dialects
+    /// without code point escapes get letters without accents (`e`) or `?` instead
+    Ascii,
+}
+
+impl Default for Glyphs {
+    fn default() -> Self {
+        Glyphs::Utf8
+    }
+}
+
+/// Roff special character for a typographic character
+///
+/// Two letter names without capitals come from the original troff, the rest are groff
+/// extensions also known to mandoc, other dialects get an ASCII approximation instead
+fn glyph_name(c: char, dialect: Dialect) -> Option<&'static str> {
+    let extended = dialect.extended_escapes();
+    Some(match c {
+        '\u{a0}' => "\\ ",
+        '\u{a9}' => "\\(co",
+        '\u{ab}' if extended => "\\(Fo",
+        '\u{ab}' => "<<",
+        '\u{ad}' => "\\%",
+        '\u{ae}' => "\\(rg",
+        '\u{b0}' => "\\(de",
+        '\u{b1}' => "\\(+-",
+        '\u{a7}' => "\\(sc",
+        '\u{b6}' => "\\(ps",
+        '\u{bb}' if extended => "\\(Fc",
+        '\u{bb}' => ">>",
+        '\u{d7}' => "\\(mu",
+        '\u{f7}' => "\\(di",
+        '\u{2010}' => "\\(hy",
+        '\u{2013}' => "\\(en",
+        '\u{2014}' => "\\(em",
+        '\u{2018}' if extended => "\\(oq",
+        '\u{2018}' => "`",
+        '\u{2019}' if extended => "\\(cq",
+        '\u{2019}' => "\\&'",
+        '\u{201c}' => "\\(lq",
+        '\u{201d}' => "\\(rq",
+        '\u{2020}' => "\\(dg",
+        '\u{2021}' => "\\(dd",
+        '\u{2022}' => "\\(bu",
+        '\u{2026}' if extended => "\\[u2026]",
+        '\u{2026}' => "\\&.\\|.\\|.",
+        '\u{2039}' if extended => "\\(fo",
+        '\u{2039}' => "<",
+        '\u{203a}' if extended => "\\(fc",
+        '\u{203a}' => ">",
+        '\u{20ac}' if extended => "\\(Eu",
+        '\u{20ac}' => "EUR",
+        '\u{2122}' => "\\(tm",
+        '\u{2190}' => "\\(<-",
+        '\u{2192}' => "\\(->",
+        '\u{2194}' => "\\(<>",
+        '\u{21d0}' => "\\(lA",
+        '\u{21d2}' => "\\(rA",
+        '\u{2260}' => "\\(!=",
+        '\u{2264}' => "\\(<=",
+        '\u{2265}' => "\\(>=",
+        _ => return None,
+    })
+}
+
+/// ASCII approximation of a character for dialects without code point escapes, Latin-1 letters
+/// lose their accents and anything else becomes `?`
+fn transliterate(c: char) -> &'static str {
+    match c {
+        '\u{c0}'..='\u{c5}' => "A",
+        '\u{c6}' => "AE",
+        '\u{c7}' => "C",
+        '\u{c8}'..='\u{cb}' => "E",
+        '\u{cc}'..='\u{cf}' => "I",
+        '\u{d0}' => "D",
+        '\u{d1}' => "N",
+        '\u{d2}'..='\u{d6}' | '\u{d8}' => "O",
+        '\u{d9}'..='\u{dc}' => "U",
+        '\u{dd}' => "Y",
+        '\u{de}' => "Th",
+        '\u{df}' => "ss",
+        '\u{e0}'..='\u{e5}' => "a",
+        '\u{e6}' => "ae",
+        '\u{e7}' => "c",
+        '\u{e8}'..='\u{eb}' => "e",
+        '\u{ec}'..='\u{ef}' => "i",
+        '\u{f0}' => "d",
+        '\u{f1}' => "n",
+        '\u{f2}'..='\u{f6}' | '\u{f8}' => "o",
+        '\u{f9}'..='\u{fc}' => "u",
+        '\u{fd}' | '\u{ff}' => "y",
+        '\u{fe}' => "th",
+        _ => "?",
+    }
+}
+
+/// Write a character, replacing it with a roff special character if configured
+///
+/// Dialects without groff style escapes get an ASCII approximation for characters without a
+/// name
+fn push_char(c: char, out: &mut Vec<u8>, glyphs: Glyphs, dialect: Dialect) {
+    if c.is_ascii() || glyphs == Glyphs::Utf8 {
+        out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+    } else if let Some(name) = glyph_name(c, dialect) {
+        out.extend_from_slice(name.as_bytes());
+    } else if glyphs == Glyphs::Typographic {
+        out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+    } else if dialect.extended_escapes() {
+        out.extend_from_slice(format!("\\[u{:04X}]", c as u32).as_bytes());
+    } else {
+        out.extend_from_slice(transliterate(c).as_bytes());
+    }
+}
+
+/// Roff implementation the output is intended for
 ///
-/// <https://bugs.debian.org/cgi-bin/bugreport.cgi?bug=507673#65>
-const APOSTROPHE: &str = "\\*(Aq";
+/// Dialects differ in available fonts, escape sequences and macros. Extension macros of man(7)
+/// such as `.EX` or `.MR` are defined in the preamble for dialects that lack them.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Dialect {
+    /// GNU roff and compatible implementations, macros are defined conditionally for older
+    /// versions that lack them
+    Groff,
+    /// mandoc, it has no constant width fonts and uses bold ones instead
+    Mandoc,
+    /// Legacy troff implementations such as Heirloom troff, without any of the extensions
+    Legacy,
+    /// Plan 9 troff, input is always UTF-8 and `.EX` is available
+    Plan9,
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Dialect::Groff
+    }
+}
+
+impl Dialect {
+    /// Escape sequence needed to set a font, None for the current font
+    ///
+    /// `\fP` used to return to the previous font comes from the original troff and works
+    /// with all the dialects
+    pub(crate) fn font(self, font: Font) -> Option<&'static str> {
+        let constant_width = self == Dialect::Groff;
+        Some(match font {
+            Font::Current => return None,
+            Font::Bold => "\\fB",
+            Font::BoldItalic if self == Dialect::Legacy => "\\fB",
+            Font::BoldItalic => "\\f(BI",
+            Font::Italic => "\\fI",
+            Font::Mono if constant_width => "\\f(CR",
+            // mandoc shows constant width text in bold on terminals
+            Font::Mono if self == Dialect::Mandoc => "\\fB",
+            Font::Mono => "\\f(CW",
+            Font::MonoBold if constant_width => "\\f(CB",
+            Font::MonoBold => "\\fB",
+            Font::MonoItalic if constant_width => "\\f(CI",
+            Font::MonoItalic => "\\fI",
+            Font::Roman => "\\fR",
+        })
+    }
+
+    /// Name of the constant width font for the `.ft` request
+    pub(crate) fn mono_font(self) -> &'static str {
+        match self {
+            Dialect::Groff => "CR",
+            Dialect::Mandoc => "B",
+            Dialect::Legacy | Dialect::Plan9 => "CW",
+        }
+    }
+
+    /// Supports groff style escape sequences such as `\[u00E9]` or `\~`
+    pub(crate) fn extended_escapes(self) -> bool {
+        matches!(self, Dialect::Groff | Dialect::Mandoc)
+    }
+
+    /// Replacement for apostrophes, if any
+    fn apostrophe(self) -> Option<&'static str> {
+        match self {
+            Dialect::Groff => Some("\\*(Aq"),
+            Dialect::Mandoc => Some("\\(aq"),
+            Dialect::Legacy | Dialect::Plan9 => None,
+        }
+    }
+
+    /// Preamble required to handle apostrophes
+    pub(crate) fn apostrophe_preamble(self) -> &'static str {
+        match self {
+            Dialect::Groff => APOSTROPHE_PREABMLE,
+            Dialect::Mandoc | Dialect::Legacy | Dialect::Plan9 => "",
+        }
+    }
+}
 
 #[allow(clippy::doc_markdown)]
 /// A preamble added to the start of rendered output.
@@ -35,7 +240,7 @@ const APOSTROPHE: &str = "\\*(Aq";
 /// Used when apostrophe handle is enabled.
 ///
 /// See: <https://bugs.debian.org/cgi-bin/bugreport.cgi?bug=507673#65>
-pub(crate) const APOSTROPHE_PREABMLE: &str = r#".ie \n(.g .ds Aq \(aq
+const APOSTROPHE_PREABMLE: &str = r#".ie \n(.g .ds Aq \(aq
 .el .ds Aq '
 "#;
 
@@ -45,14 +250,27 @@ pub(crate) enum Escape {
     /// Insert a newline character unless on a new line already
     UnescapedAtNewline,
 
-    /// Escape space characters (`' '`) and newlines (`'\n'`)
-    /// This escape is used for control sequence arguments
+    /// Escape whitespace (`' '`, `'\t'`, `'\n'`) as non-breaking spaces, replace backslashes
+    /// with `\e` and double quotes with `\(dq`.
+    /// This escape is used for control sequence arguments with non-breaking spaces
     Spaces,
 
+    /// Wrap in double quotes if needed and double the quotes inside, tabs and newlines become
+    /// spaces and backslashes are replaced with `\e`. Each slice is a single control sequence
+    /// argument
+    Quoted,
+
+    /// Similar to [`Quoted`](Escape::Quoted) but escape sequences such as `\(bu` are kept, a
+    /// backslash that can't start one is replaced with `\e`
+    QuotedRaw,
+
     /// Escape characters roff considers special:
-    /// - `' '`, `'.'` and `'\''` at the beginning of the line,
+    /// - `' '`, `'.'` and `'\''` (the no-break control character) at the beginning of the line,
     /// - `'-'` and `'\\'` when inside the body
-    /// - replace `'\''` with APOSTROPHE if Apostrophes handling is enabled
+    /// - whitespace at the end of the line
+    /// - replace `'\''` with an escape sequence if Apostrophes handling is enabled
+    /// - remove control characters other than `'\t'` and `'\n'`, roff gives some of them special
+    ///   meaning
     Special,
 
     /// Similar to [`Special`](Escape::Special) but also replaces `'\n'` with `' '`
@@ -60,73 +278,155 @@ pub(crate) enum Escape {
 
     /// Input is written as is
     Unescaped,
+
+    /// Font selection, written according to the dialect, payload is empty
+    Font(Font),
 }
 
 #[cfg(test)]
 /// Escape a sequence of string slices according to escaping rules and store results to `String`
 ///
 /// See also [`escape`] if it is desired to reuse existing storage capacity
-pub(crate) fn escape_to_string<'a, I>(items: I, ap: Apostrophes) -> String
+pub(crate) fn escape_to_string<'a, I>(items: I, ap: Apostrophes, glyphs: Glyphs) -> String
 where
     I: IntoIterator<Item = (&'a Escape, &'a str)>,
 {
     let mut res = Vec::new();
-    escape(items, &mut res, ap);
+    escape(items, &mut res, ap, glyphs, Dialect::Groff);
     String::from_utf8(res).expect("Output should be utf8 by construction")
 }
 
+/// Control characters other than tabs and newlines are removed from the input
+fn is_control(c: char) -> bool {
+    c.is_ascii_control() && c != '\t' && c != '\n'
+}
+
+/// Write a single control sequence argument, quoted if needed, `raw` arguments keep escape
+/// sequences
+fn quote_argument(arg: &str, raw: bool, out: &mut Vec<u8>, glyphs: Glyphs, dialect: Dialect) {
+    let quote = arg.is_empty() || arg.contains(|c: char| matches!(c, ' ' | '\t' | '\n' | '"'));
+    if quote {
+        out.push(b'"');
+    }
+    let mut chars = arg.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => out.extend_from_slice(b"\"\""),
+            // `\"` starts a comment and a trailing backslash would escape the closing quote
+            '\\' => match chars.peek() {
+                Some(&next)
+                    if raw && next != '"' && next != '\t' && next != '\n' && !is_control(next) =>
+                {
+                    out.push(b'\\');
+                    let mut buf = [0; 4];
+                    out.extend_from_slice(next.encode_utf8(&mut buf).as_bytes());
+                    chars.next();
+                }
+                _ => out.extend_from_slice(b"\\e"),
+            },
+            '\t' | '\n' => out.push(b' '),
+            c if is_control(c) => {}
+            c => push_char(c, out, glyphs, dialect),
+        }
+    }
+    if quote {
+        out.push(b'"');
+    }
+}
+
 /// Escape a sequence of string slices according to escaping rules
 ///
 /// Writes results to `out`, result should be a valid utf8 string as long as `out` starts empty or
 /// contains a valid utf8 sequence
-pub(crate) fn escape<'a, I>(items: I, out: &mut Vec<u8>, ap: Apostrophes)
-where
+pub(crate) fn escape<'a, I>(
+    items: I,
+    out: &mut Vec<u8>,
+    ap: Apostrophes,
+    glyphs: Glyphs,
+    dialect: Dialect,
+) where
     I: IntoIterator<Item = (&'a Escape, &'a str)>,
 {
     let mut at_line_start = true;
+    // text ends with whitespace which needs protecting if the line ends here
+    let mut trailing_space = false;
     for (&meta, payload) in items {
         if !at_line_start && meta == Escape::UnescapedAtNewline {
+            if trailing_space {
+                out.extend_from_slice(b"\\&");
+            }
             out.push(b'\n');
             at_line_start = true;
         }
-        for &c in payload.as_bytes() {
-            match meta {
-                Escape::Spaces => {
-                    if c == b' ' || c == b'\n' {
-                        out.extend_from_slice(b"\\ ");
-                    } else {
-                        out.push(c);
-                    }
+        trailing_space = false;
+        match meta {
+            Escape::Quoted | Escape::QuotedRaw => {
+                quote_argument(payload, meta == Escape::QuotedRaw, out, glyphs, dialect);
+                at_line_start = false;
+                continue;
+            }
+            Escape::Unescaped | Escape::UnescapedAtNewline => {
+                if !payload.is_empty() {
+                    out.extend_from_slice(payload.as_bytes());
+                    at_line_start = payload.ends_with('\n');
                 }
-                Escape::Special | Escape::SpecialNoNewline => {
-                    if at_line_start && (c == b'.' || c == b'\'') {
-                        out.extend_from_slice(b"\\&");
-                    }
-                    if c == b'\\' || c == b'-' {
-                        out.push(b'\\');
-                    }
-                    if ap == Apostrophes::Handle && c == b'\'' {
-                        out.extend_from_slice(APOSTROPHE.as_bytes());
-                    } else if meta == Escape::SpecialNoNewline && c == b'\n' {
-                        out.push(b' ');
-                        at_line_start = false;
-                        continue;
-                    } else {
-                        out.push(c);
-                    }
+                continue;
+            }
+            Escape::Font(font) => {
+                if let Some(escape) = dialect.font(font) {
+                    out.extend_from_slice(escape.as_bytes());
+                    at_line_start = false;
                 }
-                Escape::Unescaped | Escape::UnescapedAtNewline => {
-                    out.push(c);
+                continue;
+            }
+            Escape::Spaces | Escape::Special | Escape::SpecialNoNewline => {}
+        }
+        for c in payload.chars() {
+            if is_control(c) {
+                continue;
+            }
+            if meta == Escape::Spaces {
+                match c {
+                    ' ' | '\t' | '\n' => out.extend_from_slice(b"\\ "),
+                    '\\' => out.extend_from_slice(b"\\e"),
+                    '"' => out.extend_from_slice(b"\\(dq"),
+                    c => push_char(c, out, glyphs, dialect),
+                }
+                at_line_start = false;
+                continue;
+            }
+            let c = if meta == Escape::SpecialNoNewline && c == '\n' {
+                ' '
+            } else {
+                c
+            };
+            if at_line_start && matches!(c, '.' | '\'' | ' ') {
+                out.extend_from_slice(b"\\&");
+            }
+            if c == '\n' && trailing_space {
+                out.extend_from_slice(b"\\&");
+            }
+            if c == '\\' || c == '-' {
+                out.push(b'\\');
+            }
+            match dialect.apostrophe() {
+                Some(apostrophe) if ap == Apostrophes::Handle && c == '\'' => {
+                    out.extend_from_slice(apostrophe.as_bytes());
                 }
+                _ => push_char(c, out, glyphs, dialect),
             }
-            at_line_start = c == b'\n';
+            trailing_space = c == ' ' || c == '\t';
+            at_line_start = c == '\n';
         }
     }
+    if trailing_space {
+        out.extend_from_slice(b"\\&");
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{escape_to_string, Apostrophes, Escape};
+    use super::{escape, escape_to_string, Apostrophes, Dialect, Escape, Glyphs};
 
     #[test]
     fn sample() {
@@ -136,7 +436,92 @@ mod test {
             (Escape::Special, "test"),
             (Escape::Unescaped, "\\fP"),
         ];
-        let output = escape_to_string(items.iter().map(|p| (&p.0, p.1)), ap);
+        let output = escape_to_string(items.iter().map(|p| (&p.0, p.1)), ap, Glyphs::Utf8);
         assert_eq!("\\fItest\\fP", output);
     }
+
+    #[test]
+    fn quoted_arguments() {
+        let ap = Apostrophes::Handle;
+        let items: &[(Escape, &str)] = &[
+            (Escape::Quoted, "plain"),
+            (Escape::Unescaped, " "),
+            (Escape::Quoted, "say \"hi\""),
+            (Escape::Unescaped, " "),
+            (Escape::Quoted, ""),
+            (Escape::Unescaped, " "),
+            (Escape::Quoted, "C:\\"),
+            (Escape::Unescaped, " "),
+            (Escape::QuotedRaw, "\\(bu \\\\ \\\"no comment"),
+            (Escape::Unescaped, " "),
+            (Escape::Quoted, "\\fB\\(bu"),
+        ];
+        let output = escape_to_string(items.iter().map(|p| (&p.0, p.1)), ap, Glyphs::Utf8);
+        assert_eq!(
+            r#"plain "say ""hi""" "" C:\e "\(bu \\ \e""no comment" \efB\e(bu"#,
+            output
+        );
+    }
+
+    #[test]
+    fn glyphs() {
+        let ap = Apostrophes::DontHandle;
+        let items: &[(Escape, &str)] = &[
+            (
+                Escape::Special,
+                "caf\u{e9} \u{2014} \u{201c}ok\u{201d}\u{2026}",
+            ),
+            (Escape::Unescaped, " "),
+            (Escape::Quoted, "\u{a9} 2024"),
+        ];
+        let items = || items.iter().map(|p| (&p.0, p.1));
+        let raw = "caf\u{e9} \u{2014} \u{201c}ok\u{201d}\u{2026} \"\u{a9} 2024\"";
+        assert_eq!(escape_to_string(items(), ap, Glyphs::Utf8), raw);
+        assert_eq!(
+            escape_to_string(items(), ap, Glyphs::Typographic),
+            "caf\u{e9} \\(em \\(lqok\\(rq\\[u2026] \"\\(co 2024\""
+        );
+        assert_eq!(
+            escape_to_string(items(), ap, Glyphs::Ascii),
+            "caf\\[u00E9] \\(em \\(lqok\\(rq\\[u2026] \"\\(co 2024\""
+        );
+    }
+
+    #[test]
+    fn glyphs_without_extended_escapes() {
+        let items: &[(Escape, &str)] = &[(
+            Escape::Special,
+            "\u{ab}\u{2018}a\u{2019}\u{bb}\u{2026} 5\u{20ac} \u{2014}",
+        )];
+        let mut out = Vec::new();
+        let items = items.iter().map(|p| (&p.0, p.1));
+        escape(
+            items,
+            &mut out,
+            Apostrophes::Handle,
+            Glyphs::Ascii,
+            Dialect::Legacy,
+        );
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "<<`a\\&'>>\\&.\\|.\\|. 5EUR \\(em"
+        );
+    }
+
+    #[test]
+    fn ascii_without_code_points() {
+        let render = |dialect| {
+            let mut out = Vec::new();
+            let items = [(Escape::Special, "Caf\u{e9} \u{c6}sop \u{3a9} \u{a9}")];
+            let items = items.iter().map(|p| (&p.0, p.1));
+            escape(items, &mut out, Apostrophes::Handle, Glyphs::Ascii, dialect);
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(render(Dialect::Legacy), "Cafe AEsop ? \\(co");
+        assert_eq!(render(Dialect::Plan9), "Cafe AEsop ? \\(co");
+        assert_eq!(
+            render(Dialect::Groff),
+            "Caf\\[u00E9] \\[u00C6]sop \\[u03A9] \\(co"
+        );
+    }
 }
@@ -86,62 +86,24 @@
 //! </ul>
 //! </details>
 
+mod build;
+mod config;
+mod diff;
 mod escape;
+mod library;
+mod man;
+mod manual;
 mod monoid;
+mod options;
 #[doc(hidden)]
 pub mod roff;
 mod semantic;
 mod shared;
+mod sync;
+#[cfg(test)]
+mod test_dir;
 
 #[doc(inline)]
-pub use crate::{semantic::*, shared::*};
-
-use std::path::Path;
-
-/// Update file contents if needed and return if it was needed
-///
-/// # Example
-///
-/// One way to use this function would be to make a test like this so CI would fail if files
-/// in the repository are outdated.
-/// ```no_run
-/// # /*
-/// #[test]
-/// fn update_documentation() {
-/// # */
-/// # use ::roff::write_updated;
-///     // create some document
-///     let doc = "hello world";
-///
-///     // write file, fail the test so CI fails if new documentation
-///     // not checked in so repository is up to date if CI passes
-///     assert!(
-///         write_updated("path/to/file", doc.as_bytes()).unwrap(),
-///         "Doc changes detected, you need to commit the output."
-///     );
-/// # /*
-/// }
-/// # */
-/// ```
-///
-/// # Errors
-/// Reports any file IO errors
-pub fn write_updated<P: AsRef<Path>>(path: P, value: &[u8]) -> std::io::Result<bool> {
-    use std::fs::OpenOptions;
-    use std::io::{Read, Seek};
-    let mut file = OpenOptions::new()
-        .write(true)
-        .read(true)
-        .create(true)
-        .open(path)?;
-    let mut current_val = Vec::new();
-    file.read_to_end(&mut current_val)?;
-    if current_val == value {
-        Ok(false)
-    } else {
-        file.set_len(0)?;
-        file.seek(std::io::SeekFrom::Start(0))?;
-        std::io::Write::write_all(&mut file, value)?;
-        Ok(true)
-    }
-}
+pub use crate::{
+    build::*, config::*, library::*, manual::*, options::*, semantic::*, shared::*, sync::*,
+};
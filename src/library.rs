@@ -0,0 +1,153 @@
+//! Documentation for C library functions, usually found in section 3 manpages
+
+use crate::semantic::{literal, Doc, Write};
+
+/// C function prototype
+///
+/// Renders as a line of a `SYNOPSIS`: types and names in bold and parameter names in italic
+/// for manpages, a block of C code for markdown. To document a group of functions along with
+/// headers they need use [`LibrarySynopsis`].
+///
+/// ```rust
+/// # use ::roff::*;
+/// let strlen = FunctionDoc::new("size_t", "strlen").param("const char *", "s");
+/// let mut doc = Doc::default();
+/// doc.push(strlen);
+/// let doc = doc.render_to_markdown();
+/// let expected = "<pre><code class=\"language-c\">size_t strlen(const char *s);</code></pre>";
+///
+/// assert_eq!(doc, expected);
+/// ```
+#[derive(Debug, Clone)]
+pub struct FunctionDoc {
+    ret: String,
+    name: String,
+    params: Vec<(String, String)>,
+    variadic: bool,
+}
+
+impl FunctionDoc {
+    /// Create a prototype for a function without parameters
+    #[must_use]
+    pub fn new(ret: &str, name: &str) -> Self {
+        Self {
+            ret: ret.to_owned(),
+            name: name.to_owned(),
+            params: Vec::new(),
+            variadic: false,
+        }
+    }
+
+    /// Add a parameter with a given type and name
+    ///
+    /// Pointer types such as `char *` are attached to the parameter name without a space
+    #[must_use]
+    pub fn param(mut self, ty: &str, name: &str) -> Self {
+        self.params.push((ty.to_owned(), name.to_owned()));
+        self
+    }
+
+    /// Mark the function as taking a variable number of arguments with `...`
+    #[must_use]
+    pub fn variadic(mut self, state: bool) -> Self {
+        self.variadic = state;
+        self
+    }
+
+    /// Write the prototype without the enclosing block
+    fn write_line(&self, to: &mut Doc) {
+        to.literal(&self.ret);
+        if !self.ret.ends_with('*') {
+            to.literal(" ");
+        }
+        to.literal(&self.name).literal("(");
+        if self.params.is_empty() && !self.variadic {
+            to.literal("void");
+        }
+        for (ix, (ty, name)) in self.params.iter().enumerate() {
+            if ix > 0 {
+                to.literal(", ");
+            }
+            to.literal(ty);
+            if !ty.ends_with('*') {
+                to.literal(" ");
+            }
+            to.metavar(name);
+        }
+        if self.variadic {
+            if !self.params.is_empty() {
+                to.literal(", ");
+            }
+            to.literal("...");
+        }
+        to.literal(");");
+    }
+}
+
+impl Write for FunctionDoc {
+    fn write(&self, to: &mut Doc) {
+        to.prototype(|doc: &mut Doc| self.write_line(doc));
+    }
+}
+
+/// `SYNOPSIS` for a group of C functions along with headers they need
+///
+/// ```rust
+/// # use ::roff::*;
+/// let synopsis = LibrarySynopsis::default()
+///     .include("stdio.h")
+///     .function(FunctionDoc::new("int", "printf").param("const char *", "format").variadic(true));
+/// let mut doc = Doc::default();
+/// doc.section("Synopsis").push(synopsis);
+/// let doc = doc.render_to_manpage("PRINTF", Section::LibraryFunction, &[]);
+/// let expected = r##".SH SYNOPSIS
+/// .PP
+/// .nf
+/// \fB#include <stdio.h>
+///
+/// int printf(const char *\fIformat\fB, ...);\fP
+/// .fi
+/// "##;
+/// assert!(doc.ends_with(expected));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct LibrarySynopsis {
+    includes: Vec<String>,
+    functions: Vec<FunctionDoc>,
+}
+
+impl LibrarySynopsis {
+    /// Add a header, name is used as is inside of angle brackets
+    #[must_use]
+    pub fn include(mut self, header: &str) -> Self {
+        self.includes.push(header.to_owned());
+        self
+    }
+
+    /// Add a function prototype
+    #[must_use]
+    pub fn function(mut self, function: FunctionDoc) -> Self {
+        self.functions.push(function);
+        self
+    }
+}
+
+impl Write for LibrarySynopsis {
+    fn write(&self, to: &mut Doc) {
+        to.prototype(|doc: &mut Doc| {
+            for header in &self.includes {
+                doc.push([literal("#include <"), literal(header), literal(">\n")]);
+            }
+            // empty line between headers and functions
+            if !self.includes.is_empty() && !self.functions.is_empty() {
+                doc.literal("\n");
+            }
+            for (ix, function) in self.functions.iter().enumerate() {
+                if ix > 0 {
+                    doc.literal("\n");
+                }
+                function.write_line(doc);
+            }
+        });
+    }
+}
@@ -0,0 +1,364 @@
+//! Typed macros of the [man(7)] package
+//!
+//! [man(7)]: http://mandoc.bsd.lv/man/man.7.html
+
+use crate::roff::{Dialect, Roff};
+use crate::Section;
+use std::collections::BTreeSet;
+
+/// A man(7) macro along with its arguments, see [`Roff::man`]
+///
+/// Indents are in ens, the width of the letter `n`. Arguments are plain text, use
+/// [`Roff::control`] with [`Arg::Raw`](crate::roff::Arg::Raw) to pass escape sequences.
+#[derive(Debug, Clone, Copy)]
+pub enum Macro<'a> {
+    /// `.TH` - title heading, should go first
+    Th {
+        /// Page title, usually the name of the program in uppercase
+        title: &'a str,
+        /// Manual section
+        section: Section<'a>,
+        /// Date of the last change, goes to the middle of the footer
+        date: Option<&'a str>,
+        /// Source of the page such as a package name and a version, goes to the left of the
+        /// footer
+        source: Option<&'a str>,
+        /// Title of the manual, goes to the middle of the header
+        manual: Option<&'a str>,
+    },
+    /// `.SH` - section heading, without an argument heading is taken from the next line
+    Sh(Option<&'a str>),
+    /// `.SS` - subsection heading, without an argument heading is taken from the next line
+    Ss(Option<&'a str>),
+    /// `.PP` - start a new paragraph
+    Pp,
+    /// `.TP` - start a paragraph with a tag on the next line and an optional indent
+    Tp(Option<usize>),
+    /// `.IP` - start a paragraph with a given tag and an optional indent
+    Ip(&'a str, Option<usize>),
+    /// `.RS` - increase indentation, by a given amount or by the default one
+    Rs(Option<usize>),
+    /// `.RE` - restore indentation changed by [`Rs`](Self::Rs)
+    Re,
+    /// `.B` - text in bold
+    B(&'a str),
+    /// `.I` - text in italic
+    I(&'a str),
+    /// `.BR` - alternating bold and roman text, starting with bold, not to be confused with
+    /// the `.br` line break request. Nothing is written without any parts
+    Br(&'a [&'a str]),
+    /// `.IR` - alternating italic and roman text, starting with italic. Nothing is written
+    /// without any parts
+    Ir(&'a [&'a str]),
+    /// `.BI` - alternating bold and italic text, starting with bold. Nothing is written
+    /// without any parts
+    Bi(&'a [&'a str]),
+    /// `.SY` - start a command synopsis with a command name
+    Sy(&'a str),
+    /// `.OP` - an optional argument of a command synopsis with an optional metavariable
+    Op(&'a str, Option<&'a str>),
+    /// `.YS` - end a command synopsis
+    Ys,
+    /// `.EX` - start an example, with a monospaced font and no filling
+    Ex,
+    /// `.EE` - end an example
+    Ee,
+    /// `.UR` - start a hyperlink with a given url, the link text follows
+    Ur(&'a str),
+    /// `.UE` - end a hyperlink, with optional punctuation to go right after it
+    Ue(Option<&'a str>),
+    /// `.MT` - start an email link with a given address, the link text follows
+    Mt(&'a str),
+    /// `.ME` - end an email link, with optional punctuation to go right after it
+    Me(Option<&'a str>),
+    /// `.MR` - reference to another manual page with its name, section and optional
+    /// punctuation to go right after it
+    Mr(&'a str, Section<'a>, Option<&'a str>),
+}
+
+/// Groups of macros that are not a part of the original man(7), some of the dialects need
+/// them defined
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+pub(crate) enum Extension {
+    /// `.EX` and `.EE`
+    Example,
+    /// `.SY`, `.OP` and `.YS`
+    Synopsis,
+    /// `.UR` and `.UE`
+    Url,
+    /// `.MT` and `.ME`
+    Mail,
+    /// `.MR`
+    ManRef,
+}
+
+impl Extension {
+    /// Name of the first macro in the group, used to check if the group is defined already
+    fn name(self) -> &'static str {
+        match self {
+            Extension::Example => "EX",
+            Extension::Synopsis => "SY",
+            Extension::Url => "UR",
+            Extension::Mail => "MT",
+            Extension::ManRef => "MR",
+        }
+    }
+
+    /// Macro definitions for dialects without them
+    fn definitions(self, dialect: Dialect) -> String {
+        match self {
+            Extension::Example => format!(
+                ".de EX\n.nf\n.ft {}\n..\n.de EE\n.ft R\n.fi\n..\n",
+                dialect.mono_font()
+            ),
+            Extension::Synopsis => String::from(
+                r#".de SY
+.PP
+.B \\$1
+..
+.de OP
+[\fB\\$1\fP\c
+.if \\n(.$>1 \ \fI\\$2\fP\c
+]
+..
+.de YS
+.PP
+..
+"#,
+            ),
+            Extension::Url => String::from(
+                r#".de UR
+.ds ]U \\$1
+..
+.de UE
+\& <\\*(]U>\\$1
+..
+"#,
+            ),
+            Extension::Mail => String::from(
+                r#".de MT
+.ds ]M \\$1
+..
+.de ME
+\& <\\*(]M>\\$1
+..
+"#,
+            ),
+            Extension::ManRef => String::from(
+                r#".de MR
+\fI\\$1\fP(\\$2)\\$3
+..
+"#,
+            ),
+        }
+    }
+}
+
+/// Definitions of extension macros a dialect lacks
+///
+/// groff and mandoc get them wrapped in a check so their own definitions are used when present
+pub(crate) fn preamble(extensions: &BTreeSet<Extension>, dialect: Dialect) -> String {
+    let mut res = String::new();
+    for &ext in extensions {
+        match (dialect, ext) {
+            (Dialect::Mandoc, ext) if ext != Extension::ManRef => {}
+            (Dialect::Plan9, Extension::Example) => {}
+            (Dialect::Groff | Dialect::Mandoc, _) => {
+                res.push_str(&format!(".if !d {} \\{{\\\n", ext.name()));
+                res.push_str(&ext.definitions(dialect));
+                res.push_str(".\\}\n");
+            }
+            (Dialect::Legacy | Dialect::Plan9, _) => res.push_str(&ext.definitions(dialect)),
+        }
+    }
+    res
+}
+
+impl Roff {
+    /// Insert a man(7) macro
+    ///
+    /// Macros missing from the original man(7) are defined in the preamble when rendering for
+    /// a [`Dialect`] that lacks them
+    ///
+    /// ```rust
+    /// # use ::roff::{roff::*, Section};
+    /// let doc = Roff::new()
+    ///     .man(Macro::Th {
+    ///         title: "FOO",
+    ///         section: Section::General,
+    ///         date: None,
+    ///         source: Some("foo 1.0"),
+    ///         manual: None,
+    ///     })
+    ///     .man(Macro::Sh(Some("NAME")))
+    ///     .text([(Font::Current, "foo - do a foo thing")])
+    ///     .man(Macro::Tp(Some(4)))
+    ///     .man(Macro::Br(&["foo", "(1)"]))
+    ///     .render(Apostrophes::DontHandle);
+    /// assert_eq!(
+    ///     doc,
+    ///     ".TH FOO 1 \"\" \"foo 1.0\"\n.SH NAME\nfoo \\- do a foo thing\n.TP 4n\n.BR foo (1)\n"
+    /// );
+    /// ```
+    pub fn man(&mut self, mac: Macro<'_>) -> &mut Self {
+        let indent = |width: Option<usize>| width.map(|w| format!("{}n", w));
+        match mac {
+            Macro::Ex | Macro::Ee => self.extension(Extension::Example),
+            Macro::Sy(_) | Macro::Op(..) | Macro::Ys => self.extension(Extension::Synopsis),
+            Macro::Ur(_) | Macro::Ue(_) => self.extension(Extension::Url),
+            Macro::Mt(_) | Macro::Me(_) => self.extension(Extension::Mail),
+            Macro::Mr(..) => self.extension(Extension::ManRef),
+            _ => self,
+        };
+        match mac {
+            Macro::Th {
+                title,
+                section,
+                date,
+                source,
+                manual,
+            } => {
+                let mut extra = vec![date, source, manual];
+                while extra.last() == Some(&None) {
+                    extra.pop();
+                }
+                let extra = extra.into_iter().map(Option::unwrap_or_default);
+                let args = std::iter::once(title)
+                    .chain(std::iter::once(section.as_str()))
+                    .chain(extra);
+                self.control("TH", args)
+            }
+            Macro::Sh(name) => self.control("SH", name),
+            Macro::Ss(name) => self.control("SS", name),
+            Macro::Pp => self.control0("PP"),
+            Macro::Tp(width) => self.control("TP", indent(width)),
+            Macro::Ip(tag, width) => {
+                let width = width.map(|w| w.to_string());
+                self.control("IP", std::iter::once(tag).chain(width.as_deref()))
+            }
+            Macro::Rs(width) => self.control("RS", indent(width)),
+            Macro::Re => self.control0("RE"),
+            Macro::B(text) => self.control("B", [text]),
+            Macro::I(text) => self.control("I", [text]),
+            // without arguments these macros would apply to the next line instead
+            Macro::Br(&[]) | Macro::Ir(&[]) | Macro::Bi(&[]) => self,
+            Macro::Br(parts) => self.control("BR", parts),
+            Macro::Ir(parts) => self.control("IR", parts),
+            Macro::Bi(parts) => self.control("BI", parts),
+            Macro::Sy(command) => self.control("SY", [command]),
+            Macro::Op(name, arg) => self.control("OP", std::iter::once(name).chain(arg)),
+            Macro::Ys => self.control0("YS"),
+            Macro::Ex => self.control0("EX"),
+            Macro::Ee => self.control0("EE"),
+            Macro::Ur(url) => self.control("UR", [url]),
+            Macro::Ue(trailer) => self.control("UE", trailer),
+            Macro::Mt(address) => self.control("MT", [address]),
+            Macro::Me(trailer) => self.control("ME", trailer),
+            Macro::Mr(name, section, trailer) => self.control(
+                "MR",
+                [name, section.as_str()].iter().copied().chain(trailer),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Macro;
+    use crate::roff::{Apostrophes, Dialect, RenderOptions, Roff};
+    use crate::Section;
+
+    fn render(mac: Macro<'_>) -> String {
+        Roff::new()
+            .man(mac)
+            .render(RenderOptions::from(Apostrophes::DontHandle).dialect(Dialect::Plan9))
+    }
+
+    #[test]
+    fn title() {
+        let th = Macro::Th {
+            title: "FOO",
+            section: Section::General,
+            date: Some("2024-01-01"),
+            source: None,
+            manual: Some("Foo Manual"),
+        };
+        assert_eq!(render(th), ".TH FOO 1 2024-01-01 \"\" \"Foo Manual\"\n");
+    }
+
+    #[test]
+    fn headings() {
+        assert_eq!(render(Macro::Sh(Some("SEE ALSO"))), ".SH \"SEE ALSO\"\n");
+        assert_eq!(render(Macro::Sh(None)), ".SH\n");
+        assert_eq!(render(Macro::Ss(Some("Flags"))), ".SS Flags\n");
+        assert_eq!(render(Macro::Ss(None)), ".SS\n");
+    }
+
+    #[test]
+    fn paragraphs() {
+        assert_eq!(render(Macro::Pp), ".PP\n");
+        assert_eq!(render(Macro::Tp(None)), ".TP\n");
+        assert_eq!(render(Macro::Tp(Some(8))), ".TP 8n\n");
+        assert_eq!(render(Macro::Ip("\\(bu", None)), ".IP \\e(bu\n");
+        assert_eq!(render(Macro::Ip(" 1.", Some(4))), ".IP \" 1.\" 4\n");
+    }
+
+    #[test]
+    fn indents() {
+        assert_eq!(render(Macro::Rs(None)), ".RS\n");
+        assert_eq!(render(Macro::Rs(Some(4))), ".RS 4n\n");
+        assert_eq!(render(Macro::Re), ".RE\n");
+    }
+
+    #[test]
+    fn fonts() {
+        assert_eq!(render(Macro::B("--all")), ".B --all\n");
+        assert_eq!(render(Macro::I("file name")), ".I \"file name\"\n");
+        assert_eq!(render(Macro::Br(&["ls", "(1)"])), ".BR ls (1)\n");
+        assert_eq!(render(Macro::Ir(&["FILE", "..."])), ".IR FILE ...\n");
+        assert_eq!(render(Macro::Bi(&["-o", "FILE"])), ".BI -o FILE\n");
+    }
+
+    #[test]
+    fn empty_alternating_fonts() {
+        assert_eq!(render(Macro::Br(&[])), "");
+        assert_eq!(render(Macro::Ir(&[])), "");
+        assert_eq!(render(Macro::Bi(&[])), "");
+    }
+
+    #[test]
+    fn synopsis() {
+        let mut doc = Roff::new();
+        doc.man(Macro::Sy("ls"))
+            .man(Macro::Op("-a", None))
+            .man(Macro::Op("-w", Some("COLS")))
+            .man(Macro::Ys);
+        let page = doc.render(RenderOptions::from(Apostrophes::DontHandle));
+        assert!(page.starts_with(".if !d SY \\{\\\n.de SY\n"), "{}", page);
+        assert!(
+            page.ends_with(".\\}\n.SY ls\n.OP -a\n.OP -w COLS\n.YS\n"),
+            "{}",
+            page
+        );
+    }
+
+    #[test]
+    fn example() {
+        let mut doc = Roff::new();
+        doc.man(Macro::Ex).man(Macro::Ee);
+        assert_eq!(
+            doc.render(RenderOptions::from(Apostrophes::DontHandle).dialect(Dialect::Plan9)),
+            ".EX\n.EE\n"
+        );
+    }
+
+    #[test]
+    fn links() {
+        assert!(render(Macro::Ur("https://example.com")).ends_with(".UR https://example.com\n"));
+        assert!(render(Macro::Ue(Some(","))).ends_with(".UE ,\n"));
+        assert!(render(Macro::Ue(None)).ends_with(".UE\n"));
+        assert!(render(Macro::Mt("me@example.com")).ends_with(".MT me@example.com\n"));
+        assert!(render(Macro::Me(None)).ends_with(".ME\n"));
+        assert!(render(Macro::Mr("ls", Section::General, None)).ends_with(".MR ls 1\n"));
+    }
+}
@@ -0,0 +1,395 @@
+//! Sets of related manpages, such as a program and its subcommands
+
+use crate::semantic::{text, Doc, ManpageOptions, Write};
+use crate::{write_updated, Section};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// `NAME` section in the form indexing tools such as `mandb` and `makewhatis` expect
+///
+/// Names and a one line description are rendered as `name, alias \- description` without any
+/// styling.
+///
+/// ```rust
+/// # use ::roff::*;
+/// let mut doc = Doc::default();
+/// doc.push(NameDoc::new("gzip", "compress or expand files").alias("gunzip"));
+/// let doc = doc.render_to_manpage("GZIP", Section::General, &[]);
+///
+/// assert!(doc.ends_with(".SH NAME\ngzip, gunzip \\- compress or expand files\n"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct NameDoc {
+    names: Vec<String>,
+    description: String,
+}
+
+impl NameDoc {
+    /// Create a `NAME` section for a single name
+    #[must_use]
+    pub fn new(name: &str, description: &str) -> Self {
+        Self {
+            names: vec![name.to_owned()],
+            description: description.to_owned(),
+        }
+    }
+
+    /// Add another name documented by the same page
+    #[must_use]
+    pub fn alias(mut self, name: &str) -> Self {
+        self.names.push(name.to_owned());
+        self
+    }
+}
+
+impl Write for NameDoc {
+    fn write(&self, to: &mut Doc) {
+        // indexing tools look for this exact heading, regardless of the uppercase option
+        to.section("NAME").whatis(|doc: &mut Doc| {
+            doc.text(format!("{} - {}", self.names.join(", "), self.description));
+        });
+    }
+}
+
+/// Check that a page name can be used as a file name inside of an output directory
+pub(crate) fn check_page_name(name: &str) -> io::Result<()> {
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\'].as_ref()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{:?} can't be used as a manual page name", name),
+        ));
+    }
+    Ok(())
+}
+
+/// A single page of a [`ManualSet`]
+#[derive(Debug, Clone)]
+pub struct ManualPage<'a> {
+    name: String,
+    aliases: Vec<String>,
+    section: Section<'a>,
+    description: String,
+    doc: Doc,
+}
+
+impl<'a> ManualPage<'a> {
+    /// Create a page with a given name, section and a short description used in the `NAME`
+    /// section, contents should be block level fragments
+    pub fn new<S>(name: &str, section: Section<'a>, description: &str, content: S) -> Self
+    where
+        S: Write,
+    {
+        let mut doc = Doc::default();
+        doc.push(content);
+        Self {
+            name: name.to_owned(),
+            aliases: Vec::new(),
+            section,
+            description: description.to_owned(),
+            doc,
+        }
+    }
+
+    /// Add another name documented by the page, such as a function or a symlinked binary
+    #[must_use]
+    pub fn alias(mut self, name: &str) -> Self {
+        self.aliases.push(name.to_owned());
+        self
+    }
+
+    /// Path of the rendered page relative to the root of a manual, such as `man1/foo-build.1`
+    #[must_use]
+    pub fn path(&self) -> PathBuf {
+        let section = self.section.as_str();
+        // subsections such as 3p share a directory with their section
+        let dir = section.get(..1).unwrap_or(section);
+        PathBuf::from(format!("man{}", dir)).join(format!("{}.{}", self.name, section))
+    }
+
+    /// Check that the name can be used as a file name
+    fn check_name(&self) -> io::Result<()> {
+        check_page_name(&self.name)
+    }
+
+    /// Write a `foo(1)` style mention of the page
+    fn write_mention(&self, to: &mut Doc) {
+        to.literal(&self.name)
+            .text(format!("({})", self.section.as_str()));
+    }
+}
+
+/// A set of related manpages: a program, its subcommands and any extra pages
+///
+/// Every page gets a `NAME` section generated from its name and description and a `SEE ALSO`
+/// section mentioning the rest of the set unless it has one already, the overview page also
+/// lists all the subcommands.
+///
+/// ```rust
+/// # use ::roff::*;
+/// let mut overview = Doc::default();
+/// overview.section("Description").paragraph(text("Builds and runs things"));
+/// let mut build = Doc::default();
+/// build.section("Description").paragraph(text("Builds things"));
+///
+/// let set = ManualSet::new("foo", Section::General, "build and run things", overview)
+///     .subcommand("build", "build things", build);
+///
+/// let pages = set.render_to_manpages(&[]);
+/// assert_eq!(pages[0].0, std::path::Path::new("man1/foo.1"));
+/// assert_eq!(pages[1].0, std::path::Path::new("man1/foo-build.1"));
+/// assert!(pages[1].1.ends_with(".SH \"SEE ALSO\"\n.PP\n\\f(CBfoo\\fP\\fR(1)\\fP"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ManualSet<'a> {
+    overview: ManualPage<'a>,
+    subcommands: Vec<ManualPage<'a>>,
+    pages: Vec<ManualPage<'a>>,
+}
+
+impl<'a> ManualSet<'a> {
+    /// Create a set with an overview page for a program, contents should be block level
+    /// fragments
+    pub fn new<S>(name: &str, section: Section<'a>, description: &str, content: S) -> Self
+    where
+        S: Write,
+    {
+        Self {
+            overview: ManualPage::new(name, section, description, content),
+            subcommands: Vec::new(),
+            pages: Vec::new(),
+        }
+    }
+
+    /// Add a page for a subcommand, it is named after the program and the subcommand such as
+    /// `foo-build` and goes to the same section as the overview page
+    #[must_use]
+    pub fn subcommand<S>(mut self, name: &str, description: &str, content: S) -> Self
+    where
+        S: Write,
+    {
+        let name = format!("{}-{}", self.overview.name, name);
+        let page = ManualPage::new(&name, self.overview.section, description, content);
+        self.subcommands.push(page);
+        self
+    }
+
+    /// Add any other related page, for example describing a configuration file format
+    #[must_use]
+    pub fn page(mut self, page: ManualPage<'a>) -> Self {
+        self.pages.push(page);
+        self
+    }
+
+    /// All the pages in the set, starting with the overview page
+    pub fn iter(&self) -> impl Iterator<Item = &ManualPage<'a>> {
+        std::iter::once(&self.overview)
+            .chain(self.subcommands.iter())
+            .chain(self.pages.iter())
+    }
+
+    /// Check that names of all the pages can be used as file names
+    pub(crate) fn check_names(&self) -> io::Result<()> {
+        self.iter().try_for_each(ManualPage::check_name)
+    }
+
+    /// Combine contents of a page with generated sections
+    fn assemble(&self, page: &ManualPage<'_>) -> Doc {
+        let mut doc = Doc::default();
+        let name = page.aliases.iter().fold(
+            NameDoc::new(&page.name, &page.description),
+            |name, alias| name.alias(alias),
+        );
+        doc.push(name);
+        doc += &page.doc;
+
+        let is_overview = std::ptr::eq(page, &self.overview);
+        if is_overview && !self.subcommands.is_empty() {
+            doc.section("Commands").dlist(|doc: &mut Doc| {
+                for command in &self.subcommands {
+                    doc.definition(
+                        |doc: &mut Doc| command.write_mention(doc),
+                        text(&command.description),
+                    );
+                }
+            });
+        }
+
+        let others = self
+            .iter()
+            .filter(|other| !std::ptr::eq(*other, page))
+            .collect::<Vec<_>>();
+        if !others.is_empty() && !page.doc.has_section("See also") {
+            doc.section("See also").paragraph(|doc: &mut Doc| {
+                for (ix, other) in others.iter().enumerate() {
+                    if ix > 0 {
+                        doc.text(", ");
+                    }
+                    other.write_mention(doc);
+                }
+            });
+        }
+        doc
+    }
+
+    /// Index of all the pages and their aliases in the format of `whatis` output, sorted by name
+    ///
+    /// ```rust
+    /// # use ::roff::*;
+    /// let set = ManualSet::new("foo", Section::General, "build and run things", text(""))
+    ///     .subcommand("build", "build things", text(""));
+    /// let expected = "\
+    /// foo (1)              - build and run things
+    /// foo-build (1)        - build things
+    /// ";
+    ///
+    /// assert_eq!(set.whatis(), expected);
+    /// ```
+    #[must_use]
+    pub fn whatis(&self) -> String {
+        let mut entries = self
+            .iter()
+            .flat_map(|page| {
+                std::iter::once(&page.name)
+                    .chain(page.aliases.iter())
+                    .map(move |name| (name, page))
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        entries
+            .into_iter()
+            .map(|(name, page)| {
+                let name = format!("{} ({})", name, page.section.as_str());
+                format!("{:<20} - {}\n", name, page.description)
+            })
+            .collect()
+    }
+
+    /// Render all the pages as manpages along with their paths, see [`ManualPage::path`]
+    ///
+    /// `extra` is passed to every page, see [`Doc::render_to_manpage`]
+    #[must_use]
+    pub fn render_to_manpages(&self, extra: &[&str]) -> Vec<(PathBuf, String)> {
+        self.render_to_manpages_with(extra, &ManpageOptions::default())
+    }
+
+    /// Render all the pages as manpages with custom options
+    #[must_use]
+    pub fn render_to_manpages_with(
+        &self,
+        extra: &[&str],
+        options: &ManpageOptions,
+    ) -> Vec<(PathBuf, String)> {
+        self.iter()
+            .map(|page| {
+                let title = page.name.to_uppercase();
+                let doc = self.assemble(page);
+                let rendered = doc.render_to_manpage_with(&title, page.section, extra, options);
+                (page.path(), rendered)
+            })
+            .collect()
+    }
+
+    /// Render all the pages as markdown, files are named after pages such as `foo-build.md`
+    #[must_use]
+    pub fn render_to_markdown(&self) -> Vec<(PathBuf, String)> {
+        self.iter()
+            .map(|page| {
+                let path = PathBuf::from(format!("{}.md", page.name));
+                (path, self.assemble(page).render_to_markdown())
+            })
+            .collect()
+    }
+
+    /// Render all the pages as manpages and write them into a directory, creating `man1` and
+    /// similar subdirectories as needed
+    ///
+    /// Returns paths of the files that were updated, see [`write_updated`]
+    ///
+    /// # Errors
+    /// Reports any file IO errors and page names that can't be used as file names, such as
+    /// ones containing `/`
+    pub fn write_manpages<P: AsRef<Path>>(
+        &self,
+        dir: P,
+        extra: &[&str],
+    ) -> io::Result<Vec<PathBuf>> {
+        self.check_names()?;
+        let mut updated = Vec::new();
+        for (path, page) in self.render_to_manpages(extra) {
+            let path = dir.as_ref().join(path);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            if write_updated(&path, page.as_bytes())? {
+                updated.push(path);
+            }
+        }
+        Ok(updated)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ManualPage, ManualSet};
+    use crate::semantic::{text, ManpageOptions};
+    use crate::test_dir::TestDir;
+    use crate::Section;
+
+    #[test]
+    fn see_also() {
+        let mut custom = crate::Doc::default();
+        custom.section("See Also").paragraph(text("bar(1)"));
+        let set = ManualSet::new("foo", Section::General, "foo things", text(""))
+            .subcommand("build", "build things", text(""))
+            .page(ManualPage::new(
+                "foo.toml",
+                Section::FileFormat,
+                "config",
+                custom,
+            ));
+        let pages = set.render_to_markdown();
+        assert!(pages[0].1.contains("Commands"));
+        assert!(
+            pages[0].1.ends_with("<tt><b>foo.toml</b></tt>(5)</p>"),
+            "{}",
+            pages[0].1
+        );
+        assert!(pages[1]
+            .1
+            .ends_with("<tt><b>foo</b></tt>(1), <tt><b>foo.toml</b></tt>(5)</p>"));
+        assert_eq!(
+            pages[2].1.to_lowercase().matches("see also").count(),
+            1,
+            "{}",
+            pages[2].1
+        );
+        assert!(pages[2].1.ends_with("<p>bar(1)</p>"), "{}", pages[2].1);
+    }
+
+    #[test]
+    fn name_section() {
+        let set = ManualSet::new("foo", Section::General, "foo things", text("more"));
+        let mut options = ManpageOptions::default();
+        options.uppercase_sections(false);
+        let pages = set.render_to_manpages_with(&[], &options);
+        assert!(
+            pages[0]
+                .1
+                .contains("\n.SH NAME\nfoo \\- foo things\n\\fRmore\\fP"),
+            "{}",
+            pages[0].1
+        );
+    }
+
+    #[test]
+    fn invalid_names() {
+        let dir = TestDir::new("names");
+        for name in &["../foo", "..", "a/b", "a\\b", ""] {
+            let set = ManualSet::new("foo", Section::General, "foo things", text(""))
+                .page(ManualPage::new(name, Section::General, "bad", text("")));
+            let err = set.write_manpages(dir.path(), &[]).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput, "{}", name);
+        }
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+}
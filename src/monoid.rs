@@ -18,6 +18,17 @@ fn typical_usage() {
     assert_eq!("a: \"string \"; b: \"more string\"; d: \"!\"; ", r);
 }
 
+#[test]
+fn concatenation() {
+    let mut a = FreeMonoid::<char>::default();
+    a.push_str('a', "hello ");
+    let mut b = FreeMonoid::<char>::default();
+    b.push_str('b', "world");
+    let m = a + &b;
+    let r = m.iter().collect::<Vec<_>>();
+    assert_eq!(r, [(&'a', "hello "), (&'b', "world")]);
+}
+
 /// A Free Monoid on set of annotated string slices
 ///
 /// Where identity element is `FreeMonoid::default` and binary operation is `+`
@@ -96,7 +107,7 @@ impl<T> FreeMonoid<T> {
     }
 
     /// Iterate over annotated fragments
-    pub(crate) fn iter(&self) -> AnnotatedSlicesIter<T> {
+    pub(crate) fn iter(&self) -> AnnotatedSlicesIter<'_, T> {
         AnnotatedSlicesIter {
             current: 0,
             items: self,
@@ -134,8 +145,8 @@ impl<T: Clone> std::ops::Add<&Self> for FreeMonoid<T> {
 
 impl<T: Clone> std::ops::AddAssign<&Self> for FreeMonoid<T> {
     fn add_assign(&mut self, rhs: &Self) {
-        self.payload.push_str(&rhs.payload);
         let len = self.payload.len();
+        self.payload.push_str(&rhs.payload);
         self.labels.extend(
             rhs.labels
                 .iter()
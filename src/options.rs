@@ -0,0 +1,293 @@
+//! Structured documentation for command line options
+
+use crate::semantic::{env_var, metavar, mono, text, Doc, Write};
+
+/// Documentation for a single command line option
+///
+/// Renders as a definition list entry so it should go inside of a [`dlist`](Doc::dlist) or an
+/// [`OptionGroup`]. Hidden options are not rendered at all.
+///
+/// ```rust
+/// # use ::roff::*;
+/// let output = OptionDoc::new()
+///     .short('o')
+///     .long("output")
+///     .metavar("FILE")
+///     .help(text("Save output to a file"))
+///     .default_value("-");
+/// let mut doc = Doc::default();
+/// doc.dlist([output]);
+/// let doc = doc.render_to_markdown();
+/// let expected = "\
+/// <dl>
+/// <dt><tt><b>-o</b></tt>, <tt><b>--output</b>=<i>FILE</i></tt></dt>
+/// <dd>Save output to a file<br>Default: <tt>-</tt></dd></dl>";
+///
+/// assert_eq!(doc, expected);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct OptionDoc {
+    short: Option<char>,
+    long: Option<String>,
+    metavar: Option<String>,
+    help: Doc,
+    default: Option<String>,
+    env: Option<String>,
+    values: Vec<(String, Doc)>,
+    deprecated: bool,
+    hidden: bool,
+}
+
+impl OptionDoc {
+    /// Create documentation for an option without any names
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Short name of the option, without the dash
+    #[must_use]
+    pub fn short(mut self, name: char) -> Self {
+        self.short = Some(name);
+        self
+    }
+
+    /// Long name of the option, without the dashes
+    #[must_use]
+    pub fn long(mut self, name: &str) -> Self {
+        self.long = Some(name.to_owned());
+        self
+    }
+
+    /// Placeholder for the option argument, options without it are flags
+    #[must_use]
+    pub fn metavar(mut self, name: &str) -> Self {
+        self.metavar = Some(name.to_owned());
+        self
+    }
+
+    /// Description of the option, should contain text level fragments
+    #[must_use]
+    pub fn help<S>(mut self, help: S) -> Self
+    where
+        S: Write,
+    {
+        self.help = Doc::default();
+        self.help.push(help);
+        self
+    }
+
+    /// Value used when the option is not present
+    #[must_use]
+    pub fn default_value(mut self, value: &str) -> Self {
+        self.default = Some(value.to_owned());
+        self
+    }
+
+    /// Environment variable used when the option is not present
+    #[must_use]
+    pub fn env(mut self, name: &str) -> Self {
+        self.env = Some(name.to_owned());
+        self
+    }
+
+    /// Add one of the accepted values along with its description
+    #[must_use]
+    pub fn possible_value<S>(mut self, value: &str, description: S) -> Self
+    where
+        S: Write,
+    {
+        let mut doc = Doc::default();
+        doc.push(description);
+        self.values.push((value.to_owned(), doc));
+        self
+    }
+
+    /// Mark the option as deprecated
+    #[must_use]
+    pub fn deprecated(mut self, state: bool) -> Self {
+        self.deprecated = state;
+        self
+    }
+
+    /// Hide the option from the documentation
+    #[must_use]
+    pub fn hidden(mut self, state: bool) -> Self {
+        self.hidden = state;
+        self
+    }
+
+    fn write_term(&self, to: &mut Doc) {
+        if let Some(short) = self.short {
+            to.literal(format!("-{}", short));
+            if self.long.is_some() {
+                to.text(", ");
+            } else if let Some(meta) = &self.metavar {
+                to.push([mono(" "), metavar(meta)]);
+            }
+        }
+        if let Some(long) = &self.long {
+            to.literal(format!("--{}", long));
+            if let Some(meta) = &self.metavar {
+                to.push([mono("="), metavar(meta)]);
+            }
+        }
+    }
+
+    fn write_definition(&self, to: &mut Doc) {
+        // everything but deprecation notice and help goes on a separate line
+        let mut first = true;
+        let mut separate = |to: &mut Doc| {
+            if !first {
+                to.line_break();
+            }
+            first = false;
+        };
+        if self.deprecated {
+            separate(to);
+            to.important("Deprecated.");
+            if !self.help.is_empty() {
+                to.text(" ");
+            }
+        }
+        if !self.help.is_empty() {
+            if !self.deprecated {
+                separate(to);
+            }
+            self.help.write(to);
+        }
+        if let Some(default) = &self.default {
+            separate(to);
+            to.push([text("Default: "), mono(default)]);
+        }
+        if let Some(env) = &self.env {
+            separate(to);
+            to.push([text("Environment: "), env_var(env)]);
+        }
+        if !self.values.is_empty() {
+            separate(to);
+            to.text("Possible values:");
+            for (value, description) in &self.values {
+                to.line_break().literal(value);
+                if !description.is_empty() {
+                    to.text(": ");
+                    description.write(to);
+                }
+            }
+        }
+    }
+}
+
+impl Write for OptionDoc {
+    fn write(&self, to: &mut Doc) {
+        if self.hidden {
+            return;
+        }
+        to.definition(
+            |doc: &mut Doc| self.write_term(doc),
+            |doc: &mut Doc| self.write_definition(doc),
+        );
+    }
+}
+
+/// A group of options documented under a subsection heading
+///
+/// ```rust
+/// # use ::roff::*;
+/// let group = OptionGroup::new("Output")
+///     .option(OptionDoc::new().short('q').help(text("Be quiet")));
+/// let mut doc = Doc::default();
+/// doc.push(group);
+/// let doc = doc.render_to_manpage("FOO", Section::General, &[]);
+/// assert!(doc.ends_with(".SS Output\n.TP\n\\f(CB\\-q\\fP\n\\fRBe quiet\\fP\n.PP\n"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct OptionGroup {
+    name: String,
+    options: Vec<OptionDoc>,
+}
+
+impl OptionGroup {
+    /// Create an empty group with a given heading
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            options: Vec::new(),
+        }
+    }
+
+    /// Add an option to the group
+    #[must_use]
+    pub fn option(mut self, option: OptionDoc) -> Self {
+        self.options.push(option);
+        self
+    }
+}
+
+impl Write for OptionGroup {
+    fn write(&self, to: &mut Doc) {
+        to.subsection(&self.name).dlist(self.options.as_slice());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{OptionDoc, OptionGroup};
+    use crate::semantic::{text, Doc};
+    use crate::Section;
+
+    fn markdown(option: OptionDoc) -> String {
+        let mut doc = Doc::default();
+        doc.dlist([option]);
+        doc.render_to_markdown()
+    }
+
+    #[test]
+    fn short_with_metavar() {
+        let option = OptionDoc::new().short('j').metavar("N");
+        let expected = "<dl>\n<dt><tt><b>-j</b> <i>N</i></tt></dt>\n<dd></dd></dl>";
+        assert_eq!(markdown(option), expected);
+    }
+
+    #[test]
+    fn deprecated() {
+        let option = OptionDoc::new().long("old").deprecated(true);
+        let expected = "<dl>\n<dt><tt><b>--old</b></tt></dt>\n<dd><b>Deprecated.</b></dd></dl>";
+        assert_eq!(markdown(option), expected);
+
+        let option = OptionDoc::new()
+            .long("old")
+            .deprecated(true)
+            .help(text("Use --new"))
+            .env("OLD");
+        let expected = "<dl>\n<dt><tt><b>--old</b></tt></dt>\n<dd><b>Deprecated.</b> \
+                        Use --new<br>Environment: <tt>OLD</tt></dd></dl>";
+        assert_eq!(markdown(option), expected);
+    }
+
+    #[test]
+    fn possible_values() {
+        let option = OptionDoc::new()
+            .long("color")
+            .metavar("WHEN")
+            .possible_value("auto", text("detect a terminal"))
+            .possible_value("never", Doc::default());
+        let expected = "<dl>\n<dt><tt><b>--color</b>=<i>WHEN</i></tt></dt>\n<dd>Possible values:\
+                        <br><tt><b>auto</b></tt>: detect a terminal<br><tt><b>never</b></tt></dd></dl>";
+        assert_eq!(markdown(option), expected);
+    }
+
+    #[test]
+    fn hidden_manpage() {
+        let group = OptionGroup::new("Output")
+            .option(OptionDoc::new().long("debug").hidden(true))
+            .option(OptionDoc::new().short('v').long("verbose"));
+        let mut doc = Doc::default();
+        doc.push(group);
+        let page = doc.render_to_manpage("FOO", Section::General, &[]);
+        assert!(!page.contains("debug"), "{}", page);
+        let expected = ".SS Output\n.TP\n\\f(CB\\-v\\fP\\fR, \\fP\\f(CB\\-\\-verbose\\fP\n.PP\n";
+        assert!(page.ends_with(expected), "{}", page);
+    }
+}
@@ -26,10 +26,12 @@
 //! [groff(7)]: https://manpages.debian.org/bullseye/groff/groff.7.en.html
 //! [ROFF]: https://en.wikipedia.org/wiki/Roff_(software)
 
+use std::collections::BTreeSet;
 use std::ops::{Add, AddAssign};
 
-pub use crate::escape::Apostrophes;
-use crate::{escape::Escape, monoid::FreeMonoid};
+pub use crate::escape::{Apostrophes, Dialect, Glyphs};
+pub use crate::man::Macro;
+use crate::{escape::Escape, man::Extension, monoid::FreeMonoid};
 
 /// A Roff document with a low level interface
 ///
@@ -50,6 +52,8 @@ pub struct Roff {
     payload: FreeMonoid<Escape>,
     /// keep or strip newlines from inserted text
     pub strip_newlines: bool,
+    /// extension macros used in the document
+    extensions: BTreeSet<Extension>,
 }
 
 /// Font selector
@@ -80,26 +84,45 @@ pub enum Font {
     MonoItalic,
 }
 
-/// Escape code used to return to the previous font
-pub(crate) const RESTORE_FONT: &str = "\\fP";
+/// Control sequence argument with explicit spacing and escaping, see [`Roff::control`]
+///
+/// Plain strings are [`Quoted`](Arg::Quoted) arguments
+#[derive(Debug, Clone, Copy)]
+pub enum Arg<S> {
+    /// Argument is wrapped in double quotes if it contains spaces, spaces can be used to break
+    /// lines. Backslashes are written as `\e` so the text can't start an escape sequence
+    Quoted(S),
+    /// Same as [`Quoted`](Arg::Quoted) but escape sequences such as `\(bu` are passed through
+    Raw(S),
+    /// Spaces are replaced with `\ ` so lines are never broken on them
+    NoBreak(S),
+}
 
-impl Font {
-    /// Escape sequence needed to set this font, None for default font
-    ///
-    pub(crate) fn escape(self) -> Option<&'static str> {
+/// Types that can be used as control sequence arguments
+pub trait ControlArg {
+    /// Argument text along with the way it should be written
+    fn control_arg(&self) -> Arg<&str>;
+}
+
+impl<S: AsRef<str>> ControlArg for S {
+    fn control_arg(&self) -> Arg<&str> {
+        Arg::Quoted(self.as_ref())
+    }
+}
+
+impl<S: AsRef<str>> ControlArg for Arg<S> {
+    fn control_arg(&self) -> Arg<&str> {
         match self {
-            Font::Bold => Some("\\fB"),
-            Font::BoldItalic => Some("\\f(BI"),
-            Font::Current => None,
-            Font::Italic => Some("\\fI"),
-            Font::Mono => Some("\\f(CR"),
-            Font::MonoBold => Some("\\f(CB"),
-            Font::MonoItalic => Some("\\f(CI"),
-            Font::Roman => Some("\\fR"),
+            Arg::Quoted(s) => Arg::Quoted(s.as_ref()),
+            Arg::Raw(s) => Arg::Raw(s.as_ref()),
+            Arg::NoBreak(s) => Arg::NoBreak(s.as_ref()),
         }
     }
 }
 
+/// Escape code used to return to the previous font
+pub(crate) const RESTORE_FONT: &str = "\\fP";
+
 impl Roff {
     /// Create new raw Roff document
     #[must_use]
@@ -130,6 +153,7 @@ impl Roff {
     /// Remove all the contents, retain the allocated capacity
     pub fn clear(&mut self) {
         self.payload.clear();
+        self.extensions.clear();
     }
 
     /// Size of textual part of the payload, in bytes.
@@ -149,14 +173,27 @@ impl Roff {
     /// Insert a raw control sequence
     ///
     /// `name` should not contain initial `'.'`.
-    /// Arguments are taken from an iterator and escaped accordingly
+    /// Arguments are taken from an iterator and escaped accordingly: arguments containing spaces
+    /// are wrapped in double quotes, quotes inside are doubled and backslashes can't start escape
+    /// sequences unless the argument is [`Arg::Raw`]
+    ///
+    /// ```rust
+    /// # use ::roff::roff::*;
+    /// let doc = Roff::new()
+    ///     .control("SH", ["Section\nname with \"newline\""])
+    ///     .render(Apostrophes::DontHandle);
+    /// assert_eq!(doc, ".SH \"Section name with \"\"newline\"\"\"\n");
+    /// ```
     ///
+    /// Use [`Arg`] to choose how spaces and escape sequences are rendered for each argument
     /// ```rust
     /// # use ::roff::roff::*;
     /// let doc = Roff::new()
-    ///     .control("SH", ["Section\nname with newline"])
+    ///     .control("TP", None::<&str>)
+    ///     .control("BR", [Arg::NoBreak("foo bar"), Arg::Quoted("(1) ,")])
+    ///     .control("IP", [Arg::Raw("\\(bu"), Arg::Quoted("\\(bu")])
     ///     .render(Apostrophes::DontHandle);
-    /// assert_eq!(doc, ".SH Section\\ name\\ with\\ newline\n");
+    /// assert_eq!(doc, ".TP\n.BR foo\\ bar \"(1) ,\"\n.IP \\(bu \\e(bu\n");
     /// ```
     ///
     /// For control sequences that take no arguments you can pass `None::<&str>`
@@ -169,21 +206,21 @@ impl Roff {
     /// ```
     pub fn control<S, I>(&mut self, name: &str, args: I) -> &mut Self
     where
-        S: AsRef<str>,
+        S: ControlArg,
         I: IntoIterator<Item = S>,
     {
         self.payload.push_str(Escape::UnescapedAtNewline, ".");
         self.payload.push_str(Escape::Unescaped, name);
         for arg in args {
-            // empty macro argument can be specified as "", mostly useful for TH macro
-            // which takes several arguments positionally
-            let mut s = arg.as_ref();
-            if s.is_empty() {
-                s = "\"\"";
-            }
-            self.payload
-                .push_str(Escape::Unescaped, " ")
-                .push_str(Escape::Spaces, s);
+            self.payload.push_str(Escape::Unescaped, " ");
+            match arg.control_arg() {
+                Arg::Quoted(s) => self.payload.push_str(Escape::Quoted, s),
+                Arg::Raw(s) => self.payload.push_str(Escape::QuotedRaw, s),
+                // empty macro argument can be specified as "", mostly useful for TH macro
+                // which takes several arguments positionally
+                Arg::NoBreak("") => self.payload.push_str(Escape::Unescaped, "\"\""),
+                Arg::NoBreak(s) => self.payload.push_str(Escape::Spaces, s),
+            };
         }
         self.payload.push_str(Escape::UnescapedAtNewline, "");
         self
@@ -238,6 +275,11 @@ impl Roff {
 
     /// Insert a plain text string, special characters are escaped
     ///
+    /// Escaping is done such that no input can inject a request, a macro or an escape sequence:
+    /// backslashes are doubled, lines starting with a control character (`.` or `'`) or a space
+    /// are prefixed with `\&`, trailing whitespace is protected the same way and control
+    /// characters other than tabs and newlines are removed.
+    ///
     /// ```rust
     /// # use roff::roff::*;
     /// let doc = Roff::new()
@@ -274,13 +316,11 @@ impl Roff {
     {
         let mut prev_font = None;
         for (font, item) in text {
-            if prev_font == Some(font) {
+            if prev_font == Some(font) || font == Font::Current {
                 self.plaintext(item.as_ref());
-            } else if let Some(escape) = font.escape() {
-                self.escape(escape).plaintext(item.as_ref());
-                prev_font = Some(font);
             } else {
-                self.plaintext(item.as_ref());
+                self.font(font).plaintext(item.as_ref());
+                prev_font = Some(font);
             }
         }
         if prev_font.is_some() {
@@ -291,22 +331,148 @@ impl Roff {
 
     /// Render Roff document to `String`
     ///
-    /// This method creates a valid ROFF document which can be fed to a ROFF implementation
+    /// This method creates a valid ROFF document which can be fed to a ROFF implementation.
+    /// Takes either [`Apostrophes`] or [`RenderOptions`] for more control over the output
+    ///
+    /// ```rust
+    /// # use ::roff::roff::*;
+    /// let doc = Roff::new()
+    ///     .plaintext("\u{201c}Caf\u{e9}\u{201d} \u{2014} \u{a9} 2024")
+    ///     .render(
+    ///         RenderOptions::default()
+    ///             .apostrophes(Apostrophes::DontHandle)
+    ///             .glyphs(Glyphs::Ascii),
+    ///     );
+    /// assert_eq!(doc, "\\(lqCaf\\[u00E9]\\(rq \\(em \\(co 2024");
+    /// ```
+    ///
+    /// Fonts, apostrophes and macros missing from the original man(7) depend on the [`Dialect`],
+    /// definitions of such macros follow the `.TH` line if there is one
+    /// ```rust
+    /// # use ::roff::roff::*;
+    /// let doc = Roff::new()
+    ///     .control("TH", ["X", "1"])
+    ///     .man(Macro::Ex)
+    ///     .text([(Font::Mono, "it's")])
+    ///     .man(Macro::Ee)
+    ///     .render(RenderOptions::default().dialect(Dialect::Legacy));
+    /// assert_eq!(
+    ///     doc,
+    ///     ".TH X 1\n.de EX\n.nf\n.ft CW\n..\n.de EE\n.ft R\n.fi\n..\n.EX\n\\f(CWit's\\fP\n.EE\n"
+    /// );
+    /// ```
     #[must_use]
-    pub fn render(&self, ap: Apostrophes) -> String {
+    pub fn render<O: Into<RenderOptions>>(&self, options: O) -> String {
+        let options = options.into();
         let mut res = Vec::with_capacity(self.payload.len() * 2);
-        if ap == Apostrophes::Handle {
-            res.extend(crate::escape::APOSTROPHE_PREABMLE.as_bytes());
+        if options.apostrophes == Apostrophes::Handle {
+            res.extend(options.dialect.apostrophe_preamble().as_bytes());
         }
-        crate::escape::escape(&self.payload, &mut res, ap);
+        let mut body = Vec::with_capacity(self.payload.len() * 2);
+        crate::escape::escape(
+            &self.payload,
+            &mut body,
+            options.apostrophes,
+            options.glyphs,
+            options.dialect,
+        );
+        // macro definitions go after the title so the page still starts with it
+        let at = title_line_end(&body).unwrap_or(0);
+        res.extend(&body[..at]);
+        res.extend(crate::man::preamble(&self.extensions, options.dialect).as_bytes());
+        res.extend(&body[at..]);
         String::from_utf8(res).expect("Should be valid utf8 by construction")
     }
+
+    /// Switch to a font, escape sequence depends on the dialect
+    pub(crate) fn font(&mut self, font: Font) -> &mut Self {
+        self.payload.push_str(Escape::Font(font), "");
+        self
+    }
+
+    /// Record use of extension macros so they can be defined when needed
+    pub(crate) fn extension(&mut self, ext: Extension) -> &mut Self {
+        self.extensions.insert(ext);
+        self
+    }
+}
+
+/// Position right after the first `.TH` line of rendered output, if any
+fn title_line_end(body: &[u8]) -> Option<usize> {
+    let mut end = 0;
+    for line in body.split_inclusive(|&c| c == b'\n') {
+        end += line.len();
+        if line.starts_with(b".TH") && matches!(line.get(3), None | Some(b' ' | b'\n')) {
+            return Some(end);
+        }
+    }
+    None
+}
+
+/// Options for [`Roff::render`]
+#[derive(Debug, Clone, Copy)]
+pub struct RenderOptions {
+    apostrophes: Apostrophes,
+    glyphs: Glyphs,
+    dialect: Dialect,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            apostrophes: Apostrophes::Handle,
+            glyphs: Glyphs::Utf8,
+            dialect: Dialect::Groff,
+        }
+    }
+}
+
+impl RenderOptions {
+    /// Chainable setter for apostrophes handling, [`Apostrophes::Handle`] by default
+    pub fn apostrophes(&mut self, ap: Apostrophes) -> &mut Self {
+        self.apostrophes = ap;
+        self
+    }
+
+    /// Chainable setter for non-ASCII characters handling, [`Glyphs::Utf8`] by default
+    pub fn glyphs(&mut self, glyphs: Glyphs) -> &mut Self {
+        self.glyphs = glyphs;
+        self
+    }
+
+    /// Chainable setter for the roff implementation to target, [`Dialect::Groff`] by default
+    pub fn dialect(&mut self, dialect: Dialect) -> &mut Self {
+        self.dialect = dialect;
+        self
+    }
+}
+
+impl From<Apostrophes> for RenderOptions {
+    fn from(apostrophes: Apostrophes) -> Self {
+        Self {
+            apostrophes,
+            ..Self::default()
+        }
+    }
+}
+
+impl From<&RenderOptions> for RenderOptions {
+    fn from(options: &RenderOptions) -> Self {
+        *options
+    }
+}
+
+impl From<&mut RenderOptions> for RenderOptions {
+    fn from(options: &mut RenderOptions) -> Self {
+        *options
+    }
 }
 
 impl AddAssign<&Roff> for Roff {
     fn add_assign(&mut self, rhs: &Roff) {
         self.payload += &rhs.payload;
         self.strip_newlines = rhs.strip_newlines;
+        self.extensions.extend(&rhs.extensions);
     }
 }
 
@@ -329,7 +495,8 @@ impl<'a> Extend<&'a Roff> for Roff {
 
 #[cfg(test)]
 mod test {
-    use super::{Apostrophes, Font, Roff};
+    use super::{Apostrophes, Arg, Dialect, Font, Macro, RenderOptions, Roff};
+    use crate::Section;
     const NO_AP: Apostrophes = Apostrophes::DontHandle;
 
     #[test]
@@ -355,7 +522,25 @@ mod test {
         let text = Roff::default()
             .plaintext("foo\n.bar\n'yo\n hmm")
             .render(NO_AP);
-        assert_eq!("foo\n\\&.bar\n\\&'yo\n hmm", text);
+        assert_eq!("foo\n\\&.bar\n\\&'yo\n\\& hmm", text);
+    }
+
+    #[test]
+    fn escapes_trailing_whitespace_in_plaintext() {
+        let text = Roff::default()
+            .plaintext("foo \nbar\t")
+            .control0("PP")
+            .plaintext("baz ")
+            .render(NO_AP);
+        assert_eq!("foo \\&\nbar\t\\&\n.PP\nbaz \\&", text);
+    }
+
+    #[test]
+    fn removes_control_chars_in_plaintext() {
+        let text = Roff::default()
+            .plaintext("a\x01b\x08c\r\n\x7f.d")
+            .render(NO_AP);
+        assert_eq!("abc\n\\&.d", text);
     }
 
     #[test]
@@ -440,7 +625,39 @@ mod test {
         let text = Roff::default()
             .control("foo", ["bar", "foo and bar"])
             .render(NO_AP);
-        assert_eq!(".foo bar foo\\ and\\ bar\n", text);
+        assert_eq!(".foo bar \"foo and bar\"\n", text);
+    }
+
+    #[test]
+    fn render_control_no_break() {
+        let text = Roff::default()
+            .control("B", [Arg::NoBreak("\\fI\t\"x\"\x01")])
+            .render(NO_AP);
+        assert_eq!(".B \\efI\\ \\(dqx\\(dq\n", text);
+    }
+
+    #[test]
+    fn render_control_raw_escapes() {
+        let text = Roff::default()
+            .control("IP", [Arg::Raw("\\(bu"), Arg::Raw("2n")])
+            .man(Macro::Ip("\\(bu", None))
+            .render(NO_AP);
+        assert_eq!(".IP \\(bu 2n\n.IP \\e(bu\n", text);
+    }
+
+    #[test]
+    fn extension_macros_by_dialect() {
+        let mut doc = Roff::default();
+        doc.man(Macro::Mr("ls", Section::General, Some(",")));
+        let render = |dialect| doc.render(RenderOptions::from(NO_AP).dialect(dialect));
+        let definition = ".de MR\n\\fI\\\\$1\\fP(\\\\$2)\\\\$3\n..\n";
+        let conditional = format!(".if !d MR \\{{\\\n{}.\\}}\n.MR ls 1 ,\n", definition);
+        assert_eq!(render(Dialect::Groff), conditional);
+        assert_eq!(render(Dialect::Mandoc), conditional);
+        assert_eq!(
+            render(Dialect::Legacy),
+            format!("{}.MR ls 1 ,\n", definition)
+        );
     }
 
     #[test]
@@ -2,10 +2,13 @@
 
 use crate::{
     monoid::FreeMonoid,
-    roff::{Apostrophes, Font},
+    roff::{Arg, Dialect, Font, Glyphs, Macro, RenderOptions, Roff, RESTORE_FONT},
     shared::{Section, Style},
 };
-use std::ops::{Add, AddAssign};
+use std::{
+    borrow::Cow,
+    ops::{Add, AddAssign},
+};
 
 /// Semantic document that can be rendered as markdown or man page
 #[derive(Debug, Clone, Default)]
@@ -34,6 +37,12 @@ impl<'a> Extend<&'a Doc> for Doc {
 }
 
 impl Doc {
+    /// Check if document is empty
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
     /// Insert document section name
     ///
     /// ```rust
@@ -43,9 +52,10 @@ impl Doc {
     ///     .paragraph("Some plain text.");
     /// ```
     ///
-    /// When rendered as manpage it will use all caps
+    /// When rendered as manpage it will use all caps unless disabled with
+    /// [`ManpageOptions::uppercase_sections`]
     pub fn section(&mut self, name: &str) -> &mut Self {
-        self.push(Scoped(LogicalBlock::Section, text(name)))
+        self.heading(1, text(name))
     }
 
     /// Insert document subsection name
@@ -57,7 +67,49 @@ impl Doc {
     ///     .paragraph("Some plain text.");
     /// ```
     pub fn subsection(&mut self, name: &str) -> &mut Self {
-        self.push(Scoped(LogicalBlock::Subsection, text(name)))
+        self.heading(2, text(name))
+    }
+
+    /// Insert a heading of a given level with styled content
+    ///
+    /// Level 1 is a [`section`](Self::section), level 2 is a [`subsection`](Self::subsection),
+    /// deeper levels are rendered as bold paragraphs in a manpage since man(7) has nothing else.
+    ///
+    /// ```rust
+    /// # use ::roff::*;
+    /// let mut doc = Doc::default();
+    /// doc.heading(3, [text("The "), literal("--force"), text(" flag")]);
+    /// let doc = doc.render_to_markdown();
+    /// let expected = "### <a id=\"the-force-flag\"></a>The <tt><b>--force</b></tt> flag\n\n";
+    ///
+    /// assert_eq!(doc, expected);
+    /// ```
+    pub fn heading<S>(&mut self, level: usize, content: S) -> &mut Self
+    where
+        S: Write,
+    {
+        self.push(Scoped(LogicalBlock::Heading(level.max(1)), content))
+    }
+
+    /// Insert a heading with an explicit id
+    ///
+    /// Headings get ids derived from their text automatically, explicit ids are useful to keep
+    /// [`references`](Self::reference) valid when heading text changes
+    ///
+    /// ```rust
+    /// # use ::roff::*;
+    /// let mut doc = Doc::default();
+    /// doc.heading_with_id(1, "opts", "Options");
+    /// let doc = doc.render_to_markdown();
+    /// let expected = "# <a id=\"opts\"></a>Options\n\n";
+    ///
+    /// assert_eq!(doc, expected);
+    /// ```
+    pub fn heading_with_id<S>(&mut self, level: usize, id: &str, content: S) -> &mut Self
+    where
+        S: Write,
+    {
+        self.push(Tagged(LogicalBlock::Heading(level.max(1)), id, content))
     }
 
     /// Add a paragraph of text
@@ -77,7 +129,8 @@ impl Doc {
 
     /// Add a preformatted block of text
     ///
-    /// Paragraphs will be logically separated from each other by empty lines or indentation
+    /// Paragraphs will be logically separated from each other by empty lines or indentation,
+    /// whitespace inside is kept as is
     pub fn pre<S>(&mut self, text: S) -> &mut Self
     where
         S: Write,
@@ -85,6 +138,80 @@ impl Doc {
         self.push(Scoped(LogicalBlock::Pre, text))
     }
 
+    /// Add a block of source code, optionally annotated with a language name
+    ///
+    /// Language is used by markdown for syntax highlighting
+    ///
+    /// ```rust
+    /// # use ::roff::*;
+    /// let mut doc = Doc::default();
+    /// doc.code(Some("toml"), "[dependencies]\nroff = \"0.2\"");
+    /// let doc = doc.render_to_markdown();
+    /// let expected = "<pre><code class=\"language-toml\">[dependencies]\nroff = \"0.2\"</code></pre>";
+    ///
+    /// assert_eq!(doc, expected);
+    /// ```
+    pub fn code<S>(&mut self, language: Option<&str>, code: S) -> &mut Self
+    where
+        S: AsRef<str>,
+    {
+        self.push(Tagged(
+            LogicalBlock::Code,
+            language.unwrap_or(""),
+            text(code.as_ref()),
+        ))
+    }
+
+    /// Add an example of a shell command along with its expected output
+    ///
+    /// `output` can be empty
+    ///
+    /// ```rust
+    /// # use ::roff::*;
+    /// let mut doc = Doc::default();
+    /// doc.example("$", "cargo run -- --help", "Usage: cargo run");
+    /// let doc = doc.render_to_markdown();
+    /// let expected = "<pre><code class=\"language-console\">$ <b>cargo run -- --help</b>\nUsage: cargo run</code></pre>";
+    ///
+    /// assert_eq!(doc, expected);
+    /// ```
+    pub fn example<P, C, O>(&mut self, prompt: P, command: C, output: O) -> &mut Self
+    where
+        P: AsRef<str>,
+        C: AsRef<str>,
+        O: AsRef<str>,
+    {
+        let prompt = prompt.as_ref();
+        let output = output.as_ref();
+        self.push(Scoped(LogicalBlock::Example, |doc: &mut Doc| {
+            if !prompt.is_empty() {
+                doc.text(prompt).text(" ");
+            }
+            doc.literal(command.as_ref());
+            if !output.is_empty() {
+                doc.text("\n").text(output);
+            }
+        }))
+    }
+
+    /// Add a block of C function prototypes and include lines
+    ///
+    /// Lines are separated with newlines, see [`Prototype`](LogicalBlock::Prototype)
+    pub(crate) fn prototype<S>(&mut self, content: S) -> &mut Self
+    where
+        S: Write,
+    {
+        self.push(Scoped(LogicalBlock::Prototype, content))
+    }
+
+    /// Add the contents of a `NAME` section, see [`Whatis`](LogicalBlock::Whatis)
+    pub(crate) fn whatis<S>(&mut self, content: S) -> &mut Self
+    where
+        S: Write,
+    {
+        self.push(Scoped(LogicalBlock::Whatis, content))
+    }
+
     /// Insert a numbered list
     ///
     /// Items should contain one or more [`item`](Self::item) fragments
@@ -150,6 +277,126 @@ impl Doc {
         self
     }
 
+    /// Insert an "Exit status" section with a list of exit codes and their meanings
+    ///
+    /// ```rust
+    /// # use ::roff::*;
+    /// let mut doc = Doc::default();
+    /// doc.exit_status([(0, text("Success")), (1, text("Failure"))]);
+    /// let doc = doc.render_to_manpage("FOO", Section::General, &[]);
+    /// let expected = r#".SH "EXIT STATUS"
+    /// .TP
+    /// \f(CB0\fP
+    /// \fRSuccess\fP
+    /// .PP
+    /// .TP
+    /// \f(CB1\fP
+    /// \fRFailure\fP
+    /// .PP
+    /// "#;
+    ///
+    /// assert!(doc.ends_with(expected));
+    /// ```
+    pub fn exit_status<I, C, D>(&mut self, codes: I) -> &mut Self
+    where
+        I: IntoIterator<Item = (C, D)>,
+        C: std::fmt::Display,
+        D: Write,
+    {
+        self.section("Exit status");
+        let codes = codes.into_iter().collect::<Vec<_>>();
+        self.dlist(|doc: &mut Doc| {
+            for (code, description) in &codes {
+                doc.definition(literal(code.to_string()), |doc: &mut Doc| {
+                    description.write(doc);
+                });
+            }
+        })
+    }
+
+    /// Insert an "Environment" section with a list of environment variables, their meanings
+    /// and optional default values
+    ///
+    /// ```rust
+    /// # use ::roff::*;
+    /// let mut doc = Doc::default();
+    /// doc.environment([("EDITOR", text("Editor to use"), Some("vi"))]);
+    /// let doc = doc.render_to_markdown();
+    /// let expected = "\
+    /// ## <a id=\"environment\"></a>Environment
+    ///
+    /// <dl>
+    /// <dt><tt>EDITOR</tt></dt>
+    /// <dd>Editor to use<br>Default: <tt>vi</tt></dd></dl>";
+    ///
+    /// assert_eq!(doc, expected);
+    /// ```
+    pub fn environment<'a, I, V, D>(&mut self, vars: I) -> &mut Self
+    where
+        I: IntoIterator<Item = (V, D, Option<&'a str>)>,
+        V: AsRef<str>,
+        D: Write,
+    {
+        self.section("Environment");
+        let vars = vars.into_iter().collect::<Vec<_>>();
+        self.dlist(|doc: &mut Doc| {
+            for (var, description, default) in &vars {
+                doc.definition(env_var(var.as_ref()), |doc: &mut Doc| {
+                    description.write(doc);
+                    if let Some(default) = default {
+                        doc.line_break().push([text("Default: "), mono(*default)]);
+                    }
+                });
+            }
+        })
+    }
+
+    /// Insert an admonition block: a note, a warning, etc.
+    ///
+    /// Contents should be block level fragments such as [`paragraph`](Self::paragraph) or lists,
+    /// rendered with a label corresponding to the [`Admonition`] kind
+    ///
+    /// ```rust
+    /// # use ::roff::*;
+    /// let mut doc = Doc::default();
+    /// doc.admonition(Admonition::Warning, |doc: &mut Doc| {
+    ///     doc.paragraph([text("This will erase "), important("everything")]);
+    /// });
+    /// let doc = doc.render_to_markdown();
+    /// let expected = "<div class=\"warning\"><b>Warning:</b>\n\n<p>This will erase <b>everything</b></p>\n</div>";
+    ///
+    /// assert_eq!(doc, expected);
+    /// ```
+    pub fn admonition<S>(&mut self, kind: Admonition, content: S) -> &mut Self
+    where
+        S: Write,
+    {
+        self.push(Scoped(LogicalBlock::Admonition(kind), content))
+    }
+
+    /// Insert an indented block, such as a quote
+    ///
+    /// Contents should be block level fragments, indented blocks can be nested. `width` sets
+    /// indentation in characters for manpages, markdown renders the block as a quote.
+    ///
+    /// ```rust
+    /// # use ::roff::*;
+    /// let mut doc = Doc::default();
+    /// doc.indent(None, |doc: &mut Doc| {
+    ///     doc.paragraph("Quoted text");
+    /// });
+    /// let doc = doc.render_to_markdown();
+    /// let expected = "<blockquote>\n\n<p>Quoted text</p>\n</blockquote>";
+    ///
+    /// assert_eq!(doc, expected);
+    /// ```
+    pub fn indent<S>(&mut self, width: Option<usize>, content: S) -> &mut Self
+    where
+        S: Write,
+    {
+        self.push(Scoped(LogicalBlock::Indent(width), content))
+    }
+
     /// Append a semantic fragment to a document
     ///
     /// `push` consumes semantic fragment, if you only have a referece to it you
@@ -230,7 +477,8 @@ impl Doc {
 
     /// Plain text fragment
     ///
-    /// This fragment represents usual text, newlines are going to be ignored
+    /// This fragment represents usual text, runs of whitespace including newlines are collapsed
+    /// into a single space, use [`line_break`](Self::line_break) to start a new line
     ///
     /// ```rust
     /// # use ::roff::*;
@@ -267,6 +515,150 @@ impl Doc {
     {
         self.push(important(payload.as_ref()))
     }
+
+    /// Environment variable name fragment
+    ///
+    /// ```rust
+    /// # use ::roff::*;
+    /// let mut doc = Doc::default();
+    /// doc.text("Defaults to ").env_var("HOME");
+    /// let doc = doc.render_to_manpage("FOO", Section::General, &[]);
+    ///
+    /// assert!(doc.ends_with("\\fRDefaults to \\fP\\fBHOME\\fP"));
+    /// ```
+    pub fn env_var<S>(&mut self, payload: S) -> &mut Self
+    where
+        S: AsRef<str>,
+    {
+        self.push(env_var(payload.as_ref()))
+    }
+
+    /// Styled fragment with nested content
+    ///
+    /// Style applies to everything inside and combines with styles of nested fragments
+    ///
+    /// ```rust
+    /// # use ::roff::*;
+    /// let mut doc = Doc::default();
+    /// doc.span(Style::Important, [text("never use "), literal("--force")]);
+    /// let doc = doc.render_to_markdown();
+    /// let expected = "<b>never use <tt>--force</tt></b>";
+    ///
+    /// assert_eq!(doc, expected);
+    /// ```
+    pub fn span<S>(&mut self, style: Style, content: S) -> &mut Self
+    where
+        S: Write,
+    {
+        self.push(span(style, content))
+    }
+
+    /// Hyperlink fragment with nested content
+    ///
+    /// ```rust
+    /// # use ::roff::*;
+    /// let mut doc = Doc::default();
+    /// doc.link("https://crates.io", text("crates.io"));
+    /// let doc = doc.render_to_markdown();
+    /// let expected = "<a href=\"https://crates.io\">crates.io</a>";
+    ///
+    /// assert_eq!(doc, expected);
+    /// ```
+    pub fn link<S>(&mut self, target: &str, content: S) -> &mut Self
+    where
+        S: Write,
+    {
+        self.push(link(target, content))
+    }
+
+    /// Explicit line break
+    ///
+    /// Unlike newlines in the text which are treated as spaces this starts a new line
+    ///
+    /// ```rust
+    /// # use ::roff::*;
+    /// let mut doc = Doc::default();
+    /// doc.paragraph(|doc: &mut Doc| {
+    ///     doc.text("first line").line_break().text("second line");
+    /// });
+    /// let doc = doc.render_to_markdown();
+    /// let expected = "<p>first line<br>second line</p>";
+    ///
+    /// assert_eq!(doc, expected);
+    /// ```
+    pub fn line_break(&mut self) -> &mut Self {
+        self.push(LineBreak)
+    }
+
+    /// Special typographic character
+    ///
+    /// Prefer this to approximations such as `--` for a dash: each backend renders glyphs in
+    /// its own way, markdown uses HTML entities and manpages use roff special characters
+    ///
+    /// ```rust
+    /// # use ::roff::*;
+    /// let mut doc = Doc::default();
+    /// doc.paragraph(|doc: &mut Doc| {
+    ///     doc.text("Fast").glyph(Glyph::EmDash).text("usually");
+    /// });
+    /// assert_eq!(doc.render_to_markdown(), "<p>Fast&mdash;usually</p>");
+    /// assert!(doc
+    ///     .render_to_manpage("FOO", Section::General, &[])
+    ///     .ends_with(".PP\n\\fRFast\\fP\\(em\\fRusually\\fP"));
+    /// ```
+    pub fn glyph(&mut self, glyph: Glyph) -> &mut Self {
+        self.push(glyph)
+    }
+
+    /// Reference to a heading in the same document
+    ///
+    /// `id` is either explicitly set with [`heading_with_id`](Self::heading_with_id) or derived
+    /// from the heading text: lowercase with spaces replaced by dashes, with a numeric suffix
+    /// added in case of collisions. Markdown renders it as a link, manpage as the heading text
+    /// in quotes.
+    ///
+    /// ```rust
+    /// # use ::roff::*;
+    /// let mut doc = Doc::default();
+    /// doc.section("Exit status")
+    ///     .paragraph(|doc: &mut Doc| {
+    ///         doc.text("See ").reference("exit-status");
+    ///     });
+    /// let doc = doc.render_to_markdown();
+    /// let expected = "# <a id=\"exit-status\"></a>Exit status\n\n\
+    ///                 <p>See <a href=\"#exit-status\">Exit status</a></p>";
+    ///
+    /// assert_eq!(doc, expected);
+    /// ```
+    pub fn reference(&mut self, id: &str) -> &mut Self {
+        self.push(reference(id))
+    }
+
+    /// Footnote with text level content
+    ///
+    /// Footnotes are replaced with numbered references, contents go to the end of the document:
+    /// a numbered list in markdown and a NOTES section in a manpage.
+    ///
+    /// ```rust
+    /// # use ::roff::*;
+    /// let mut doc = Doc::default();
+    /// doc.paragraph(|doc: &mut Doc| {
+    ///     doc.text("Fast").footnote(text("On a good day"));
+    /// });
+    /// let doc = doc.render_to_manpage("FOO", Section::General, &[]);
+    /// assert!(doc.ends_with("\
+    /// .PP
+    /// \\fRFast\\fP\\fR[1]\\fP
+    /// .SH NOTES
+    /// .IP \" 1.\" 4
+    /// \\fROn a good day\\fP"));
+    /// ```
+    pub fn footnote<S>(&mut self, content: S) -> &mut Self
+    where
+        S: Write,
+    {
+        self.push(footnote(content))
+    }
 }
 
 /*
@@ -316,7 +708,7 @@ where
     S: Write,
 {
     fn write(&self, to: &mut Doc) {
-        self.as_slice().write(to);
+        (&self[..]).write(to);
     }
 }
 
@@ -331,6 +723,12 @@ where
     }
 }
 
+impl Write for Doc {
+    fn write(&self, to: &mut Doc) {
+        *to += self;
+    }
+}
+
 impl Write for &str {
     fn write(&self, to: &mut Doc) {
         to.0.push_str(Sem::Style(Style::Text), self);
@@ -347,7 +745,26 @@ impl Write for char {
 enum Sem {
     BlockStart(LogicalBlock),
     BlockEnd(LogicalBlock),
+    InlineStart(Inline),
+    InlineEnd(Inline),
+    LineBreak,
+    Glyph(Glyph),
+    /// Reference to a heading, id is stored in the payload
+    Reference,
+    Style(Style),
+}
+
+/// Inline fragment that contains other inline fragments
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Inline {
+    /// Style applied on top of styles of nested fragments
     Style(Style),
+
+    /// Hyperlink, link target is stored in the payload of `InlineStart`
+    Link,
+
+    /// Footnote, renderers get contents separately with [`Doc::split_footnotes`]
+    Footnote,
 }
 
 /// Logical block of text
@@ -355,10 +772,8 @@ enum Sem {
 /// List items are nested within lists, otherwise they should go on the top level
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 enum LogicalBlock {
-    /// Section header
-    Section,
-    /// Subsection header
-    Subsection,
+    /// Section header of a given level, 1 is for sections, 2 is for subsections
+    Heading(usize),
 
     /// A paragraph of text - in general text should not go into the doc as is
     Paragraph,
@@ -366,6 +781,19 @@ enum LogicalBlock {
     /// A preformatted block of text
     Pre,
 
+    /// A block of source code, language name is stored in the payload of `BlockStart`
+    Code,
+
+    /// A shell command with expected output
+    Example,
+
+    /// C function prototypes, `Literal` fragments are rendered in bold and `Metavar` in italic
+    Prototype,
+
+    /// Contents of the `NAME` section, rendered without any styling in manpages so `mandb`
+    /// and `makewhatis` can parse it
+    Whatis,
+
     /// Unnumbered list, put `ListItem` inside
     UnnumberedList,
     /// Numbered list, put `ListItem` inside
@@ -379,6 +807,50 @@ enum LogicalBlock {
 
     /// List items, go in all types of lists
     ListItem,
+
+    /// Labeled block containing other blocks
+    Admonition(Admonition),
+
+    /// Indented block containing other blocks, with optional width in characters
+    Indent(Option<usize>),
+}
+
+/// Kind of an [`admonition`](Doc::admonition) block
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Admonition {
+    /// Additional information user might want to know
+    Note,
+    /// Advice on how to do things better
+    Tip,
+    /// Something that can go wrong
+    Warning,
+    /// Something that can go wrong in a way that is hard to undo
+    Caution,
+    /// Feature that is going to be removed
+    Deprecated,
+}
+
+impl Admonition {
+    fn label(self) -> &'static str {
+        match self {
+            Admonition::Note => "Note",
+            Admonition::Tip => "Tip",
+            Admonition::Warning => "Warning",
+            Admonition::Caution => "Caution",
+            Admonition::Deprecated => "Deprecated",
+        }
+    }
+
+    /// Html class used for markdown, rustdoc has styles for `warning`
+    fn class(self) -> &'static str {
+        match self {
+            Admonition::Note => "note",
+            Admonition::Tip => "tip",
+            Admonition::Warning => "warning",
+            Admonition::Caution => "caution",
+            Admonition::Deprecated => "deprecated",
+        }
+    }
 }
 
 impl<S> Write for (Style, S)
@@ -493,30 +965,532 @@ where
     (Style::Important, payload)
 }
 
-struct Scoped<T>(pub LogicalBlock, pub T);
-impl<S> Write for Scoped<S>
+/// <tt>Environment variable</tt> name fragment
+///
+/// Rendered in bold in manpages
+pub fn env_var<T>(payload: T) -> (Style, T)
 where
-    S: Write,
+    T: AsRef<str>,
 {
+    (Style::EnvVar, payload)
+}
+
+/// Explicit line break fragment, see [`Doc::line_break`]
+#[derive(Debug, Copy, Clone)]
+pub struct LineBreak;
+
+impl Write for LineBreak {
     fn write(&self, to: &mut Doc) {
         to.0.squash = false;
-        to.0.push_str(Sem::BlockStart(self.0), "");
-        self.1.write(to);
-        to.0.squash = false;
-        to.0.push_str(Sem::BlockEnd(self.0), "");
+        to.0.push_str(Sem::LineBreak, "");
     }
 }
 
-// -------------------------------------------------------------
-
-/// Make it so new text is inserted at a new line
-fn at_newline(res: &mut String) {
-    if !(res.is_empty() || res.ends_with('\n')) {
-        res.push('\n');
-    }
+/// Special typographic character, see [`Doc::glyph`]
+///
+/// Glyphs are always written as roff special characters or html entities, unlike non-ASCII
+/// characters inside of text which are written according to [`Glyphs`]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Glyph {
+    /// Em dash, `—`
+    EmDash,
+    /// En dash, `–`, mostly used for ranges
+    EnDash,
+    /// Space that can't be used to break a line
+    NonBreakingSpace,
+    /// Ellipsis, `…`
+    Ellipsis,
+    /// Bullet, `•`
+    Bullet,
+    /// Copyright sign, `©`
+    Copyright,
+    /// Left double quotation mark, `“`
+    LeftQuote,
+    /// Right double quotation mark, `”`
+    RightQuote,
+    /// Left single quotation mark, `‘`
+    LeftSingleQuote,
+    /// Right single quotation mark, `’`
+    RightSingleQuote,
+    /// Leftwards arrow, `←`
+    LeftArrow,
+    /// Rightwards arrow, `→`
+    RightArrow,
 }
 
-/// Make it so new text is separated by an empty line
+impl Glyph {
+    /// Unicode character for the glyph, for output that supports it
+    #[must_use]
+    pub fn unicode(self) -> char {
+        match self {
+            Glyph::EmDash => '\u{2014}',
+            Glyph::EnDash => '\u{2013}',
+            Glyph::NonBreakingSpace => '\u{a0}',
+            Glyph::Ellipsis => '\u{2026}',
+            Glyph::Bullet => '\u{2022}',
+            Glyph::Copyright => '\u{a9}',
+            Glyph::LeftQuote => '\u{201c}',
+            Glyph::RightQuote => '\u{201d}',
+            Glyph::LeftSingleQuote => '\u{2018}',
+            Glyph::RightSingleQuote => '\u{2019}',
+            Glyph::LeftArrow => '\u{2190}',
+            Glyph::RightArrow => '\u{2192}',
+        }
+    }
+
+    /// Roff special character
+    fn roff(self, dialect: Dialect) -> &'static str {
+        match self {
+            Glyph::EmDash => "\\(em",
+            Glyph::EnDash => "\\(en",
+            Glyph::NonBreakingSpace if dialect.extended_escapes() => "\\~",
+            Glyph::NonBreakingSpace => "\\ ",
+            Glyph::Ellipsis => "\\&.\\|.\\|.",
+            Glyph::Bullet => "\\(bu",
+            Glyph::Copyright => "\\(co",
+            Glyph::LeftQuote => "\\(lq",
+            Glyph::RightQuote => "\\(rq",
+            Glyph::LeftSingleQuote if dialect.extended_escapes() => "\\(oq",
+            Glyph::LeftSingleQuote => "`",
+            Glyph::RightSingleQuote if dialect.extended_escapes() => "\\(cq",
+            Glyph::RightSingleQuote => "\\&'",
+            Glyph::LeftArrow => "\\(<-",
+            Glyph::RightArrow => "\\(->",
+        }
+    }
+
+    /// HTML entity
+    fn html(self) -> &'static str {
+        match self {
+            Glyph::EmDash => "&mdash;",
+            Glyph::EnDash => "&ndash;",
+            Glyph::NonBreakingSpace => "&nbsp;",
+            Glyph::Ellipsis => "&hellip;",
+            Glyph::Bullet => "&bull;",
+            Glyph::Copyright => "&copy;",
+            Glyph::LeftQuote => "&ldquo;",
+            Glyph::RightQuote => "&rdquo;",
+            Glyph::LeftSingleQuote => "&lsquo;",
+            Glyph::RightSingleQuote => "&rsquo;",
+            Glyph::LeftArrow => "&larr;",
+            Glyph::RightArrow => "&rarr;",
+        }
+    }
+}
+
+impl Write for Glyph {
+    fn write(&self, to: &mut Doc) {
+        to.0.squash = false;
+        to.0.push_str(Sem::Glyph(*self), "");
+    }
+}
+
+/// Reference to a heading in the same document, created with [`reference()`]
+#[derive(Debug, Copy, Clone)]
+pub struct Reference<'a>(&'a str);
+
+/// Reference to a heading in the same document
+///
+/// See [`Doc::reference`] for details
+pub fn reference(id: &str) -> Reference<'_> {
+    Reference(id)
+}
+
+impl Write for Reference<'_> {
+    fn write(&self, to: &mut Doc) {
+        to.0.squash = false;
+        to.0.push_str(Sem::Reference, self.0);
+    }
+}
+
+/// Footnote with nested content, created with [`footnote`]
+#[derive(Debug, Copy, Clone)]
+pub struct Footnote<T>(T);
+
+/// Footnote with nested content
+///
+/// See [`Doc::footnote`] for details
+///
+/// ```rust
+/// # use ::roff::*;
+/// let mut doc = Doc::default();
+/// doc.paragraph(|doc: &mut Doc| {
+///     doc.text("Fast").push(footnote(text("On a good day")));
+/// });
+/// let doc = doc.render_to_markdown();
+/// let expected = "\
+/// <p>Fast<sup id=\"fnref-1\"><a href=\"#fn-1\">1</a></sup></p>
+///
+/// <ol class=\"footnotes\">
+/// <li id=\"fn-1\">On a good day <a href=\"#fnref-1\">\u{21a9}</a></li></ol>";
+///
+/// assert_eq!(doc, expected);
+/// ```
+pub fn footnote<T>(content: T) -> Footnote<T>
+where
+    T: Write,
+{
+    Footnote(content)
+}
+
+impl<T> Write for Footnote<T>
+where
+    T: Write,
+{
+    fn write(&self, to: &mut Doc) {
+        to.0.squash = false;
+        to.0.push_str(Sem::InlineStart(Inline::Footnote), "");
+        self.0.write(to);
+        to.0.squash = false;
+        to.0.push_str(Sem::InlineEnd(Inline::Footnote), "");
+    }
+}
+
+/// Styled fragment with nested content, created with [`span`]
+#[derive(Debug, Copy, Clone)]
+pub struct Span<T>(Style, T);
+
+/// Styled fragment with nested content
+///
+/// Unlike [`literal`], [`important`] and others `span` can contain any other fragments, style
+/// of the span combines with the styles of the fragments inside
+///
+/// ```rust
+/// # use ::roff::*;
+/// let mut doc = Doc::default();
+/// doc.push(span(Style::Important, [text("never use "), literal("--force")]));
+/// let doc = doc.render_to_markdown();
+/// let expected = "<b>never use <tt>--force</tt></b>";
+///
+/// assert_eq!(doc, expected);
+/// ```
+pub fn span<T>(style: Style, content: T) -> Span<T>
+where
+    T: Write,
+{
+    Span(style, content)
+}
+
+impl<T> Write for Span<T>
+where
+    T: Write,
+{
+    fn write(&self, to: &mut Doc) {
+        to.0.squash = false;
+        to.0.push_str(Sem::InlineStart(Inline::Style(self.0)), "");
+        self.1.write(to);
+        to.0.squash = false;
+        to.0.push_str(Sem::InlineEnd(Inline::Style(self.0)), "");
+    }
+}
+
+/// Hyperlink with nested content, created with [`link`]
+#[derive(Debug, Copy, Clone)]
+pub struct Link<'a, T>(&'a str, T);
+
+/// Hyperlink with nested content
+///
+/// Markdown renders it as a link, manpage places the target after the content
+///
+/// ```rust
+/// # use ::roff::*;
+/// let mut doc = Doc::default();
+/// doc.push(link("https://docs.rs", [text("see "), metavar("CRATE"), text(" docs")]));
+/// let doc = doc.render_to_markdown();
+/// let expected = "<a href=\"https://docs.rs\">see <tt><i>CRATE</i></tt> docs</a>";
+///
+/// assert_eq!(doc, expected);
+/// ```
+pub fn link<T>(target: &str, content: T) -> Link<'_, T>
+where
+    T: Write,
+{
+    Link(target, content)
+}
+
+impl<T> Write for Link<'_, T>
+where
+    T: Write,
+{
+    fn write(&self, to: &mut Doc) {
+        to.0.squash = false;
+        to.0.push_str(Sem::InlineStart(Inline::Link), self.0);
+        self.1.write(to);
+        to.0.squash = false;
+        to.0.push_str(Sem::InlineEnd(Inline::Link), "");
+    }
+}
+
+struct Scoped<T>(pub LogicalBlock, pub T);
+impl<S> Write for Scoped<S>
+where
+    S: Write,
+{
+    fn write(&self, to: &mut Doc) {
+        to.0.squash = false;
+        to.0.push_str(Sem::BlockStart(self.0), "");
+        self.1.write(to);
+        to.0.squash = false;
+        to.0.push_str(Sem::BlockEnd(self.0), "");
+    }
+}
+
+/// Same as [`Scoped`] but also stores a string in the payload of `BlockStart`
+struct Tagged<'a, T>(pub LogicalBlock, pub &'a str, pub T);
+impl<S> Write for Tagged<'_, S>
+where
+    S: Write,
+{
+    fn write(&self, to: &mut Doc) {
+        to.0.squash = false;
+        to.0.push_str(Sem::BlockStart(self.0), self.1);
+        self.2.write(to);
+        to.0.squash = false;
+        to.0.push_str(Sem::BlockEnd(self.0), "");
+    }
+}
+
+// -------------------------------------------------------------
+
+/// Make it so new text is inserted at a new line
+fn at_newline(res: &mut String) {
+    if !(res.is_empty() || res.ends_with('\n')) {
+        res.push('\n');
+    }
+}
+
+/// Heading as seen by the renderers
+struct HeadingInfo {
+    level: usize,
+    /// Unique id, either explicit or generated from the title
+    id: String,
+    /// Heading text without styles
+    title: String,
+}
+
+/// Generate an id from a heading title
+///
+/// Alphanumeric characters are kept in lowercase, spaces, dashes and underscores become dashes
+fn slugify(title: &str) -> String {
+    let mut res = String::new();
+    for c in title.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            res.push(c);
+        } else if matches!(c, ' ' | '-' | '_') && !res.is_empty() && !res.ends_with('-') {
+            res.push('-');
+        }
+    }
+    while res.ends_with('-') {
+        res.pop();
+    }
+    if res.is_empty() {
+        res.push_str("section");
+    }
+    res
+}
+
+impl Doc {
+    /// Check if there's a section with a given title, ignoring ASCII case
+    pub(crate) fn has_section(&self, title: &str) -> bool {
+        self.headings()
+            .iter()
+            .any(|h| h.level == 1 && h.title.eq_ignore_ascii_case(title))
+    }
+
+    /// Collect all the headings in the document order, assigning unique ids
+    fn headings(&self) -> Vec<HeadingInfo> {
+        let mut res = Vec::new();
+        let mut current = None;
+        // footnotes are not a part of the title
+        let mut footnotes = 0;
+        let mut whitespace = Whitespace::default();
+        for (meta, payload) in &self.0 {
+            whitespace.track(*meta);
+            match meta {
+                Sem::InlineStart(Inline::Footnote) => footnotes += 1,
+                Sem::InlineEnd(Inline::Footnote) => footnotes -= 1,
+                _ if footnotes > 0 => {}
+                Sem::BlockStart(LogicalBlock::Heading(level)) => {
+                    current = Some(HeadingInfo {
+                        level: *level,
+                        id: payload.to_string(),
+                        title: String::new(),
+                    });
+                }
+                Sem::BlockEnd(LogicalBlock::Heading(_)) => res.extend(current.take()),
+                Sem::Glyph(glyph) => {
+                    if let Some(heading) = &mut current {
+                        heading.title.push(glyph.unicode());
+                    }
+                }
+                Sem::Style(style) => {
+                    if let Some(heading) = &mut current {
+                        heading.title.push_str(&whitespace.apply(*style, payload));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // explicit ids take priority, generated ones get a numeric suffix on collision
+        let mut used = res
+            .iter()
+            .map(|h| h.id.clone())
+            .filter(|id| !id.is_empty())
+            .collect::<std::collections::HashSet<_>>();
+        for heading in &mut res {
+            if heading.id.is_empty() {
+                let slug = slugify(&heading.title);
+                let mut id = slug.clone();
+                let mut suffix = 0;
+                while used.contains(&id) {
+                    suffix += 1;
+                    id = format!("{}-{}", slug, suffix);
+                }
+                used.insert(id.clone());
+                heading.id = id;
+            }
+        }
+        res
+    }
+
+    /// Move contents of footnotes out of the document
+    ///
+    /// Returns a document with empty footnotes left in place of references and contents of
+    /// footnotes in order. Footnotes nested inside footnotes are moved out as well and go after
+    /// the top level ones. Each reference gets the number of its footnote as a payload.
+    fn split_footnotes(&self) -> (Doc, Vec<Doc>) {
+        let mut notes = Vec::new();
+        let body = self.extract_footnotes(&mut notes);
+        let mut ix = 0;
+        while ix < notes.len() {
+            let note = std::mem::take(&mut notes[ix]);
+            notes[ix] = note.extract_footnotes(&mut notes);
+            ix += 1;
+        }
+        (body, notes)
+    }
+
+    /// Move contents of top level footnotes to the end of `notes`, nested ones stay inside
+    fn extract_footnotes(&self, notes: &mut Vec<Doc>) -> Doc {
+        let mut body = Doc::default();
+        let mut depth = 0;
+        for (meta, payload) in &self.0 {
+            match meta {
+                Sem::InlineStart(Inline::Footnote) if depth == 0 => {
+                    depth += 1;
+                    notes.push(Doc::default());
+                    body.0.push_str(*meta, &notes.len().to_string());
+                }
+                Sem::InlineEnd(Inline::Footnote) if depth == 1 => {
+                    depth -= 1;
+                    body.0.push_str(*meta, payload);
+                }
+                _ if depth > 0 => {
+                    match meta {
+                        Sem::InlineStart(Inline::Footnote) => depth += 1,
+                        Sem::InlineEnd(Inline::Footnote) => depth -= 1,
+                        _ => {}
+                    }
+                    if let Some(note) = notes.last_mut() {
+                        note.0.push_str(*meta, payload);
+                    }
+                }
+                _ => {
+                    body.0.push_str(*meta, payload);
+                }
+            }
+        }
+        body
+    }
+
+    /// Find a heading by its id
+    fn find_heading<'a>(headings: &'a [HeadingInfo], id: &str) -> Option<&'a HeadingInfo> {
+        headings.iter().find(|h| h.id == id)
+    }
+}
+
+/// Whitespace handling shared by the renderers
+///
+/// Runs of whitespace in styled text are collapsed into a single space unless text is
+/// monospaced or inside of a preformatted block
+#[derive(Default)]
+struct Whitespace {
+    /// Inside of a preformatted block
+    pre: bool,
+    /// Last fragment ended with a whitespace or it was a start of a block
+    space: bool,
+}
+
+impl Whitespace {
+    /// Update state according to a document structure event
+    fn track(&mut self, meta: Sem) {
+        match meta {
+            Sem::BlockStart(b) | Sem::BlockEnd(b) => {
+                if matches!(
+                    b,
+                    LogicalBlock::Pre
+                        | LogicalBlock::Code
+                        | LogicalBlock::Example
+                        | LogicalBlock::Prototype
+                ) {
+                    self.pre = meta == Sem::BlockStart(b);
+                }
+                self.space = true;
+            }
+            Sem::LineBreak => self.space = true,
+            Sem::Reference | Sem::Glyph(_) => self.space = false,
+            Sem::InlineStart(_) | Sem::InlineEnd(_) | Sem::Style(_) => {}
+        }
+    }
+
+    /// Collapse whitespace in a fragment of text if needed
+    fn apply<'a>(&mut self, style: Style, payload: &'a str) -> Cow<'a, str> {
+        if self.pre || style == Style::Mono {
+            if !payload.is_empty() {
+                self.space = payload.ends_with(|c: char| c.is_ascii_whitespace());
+            }
+            return Cow::Borrowed(payload);
+        }
+        let mut res = String::with_capacity(payload.len());
+        for c in payload.chars() {
+            if c.is_ascii_whitespace() {
+                if !self.space {
+                    res.push(' ');
+                }
+                self.space = true;
+            } else {
+                res.push(c);
+                self.space = false;
+            }
+        }
+        Cow::Owned(res)
+    }
+}
+
+/// Append text escaping characters html considers special
+fn push_html_escaped(res: &mut String, payload: &str) {
+    for c in payload.chars() {
+        match c {
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '&' => res.push_str("&amp;"),
+            _ => res.push(c),
+        }
+    }
+}
+
+/// Append text for use inside of a double quoted html attribute
+fn push_attr_escaped(res: &mut String, payload: &str) {
+    for c in payload.chars() {
+        match c {
+            '"' => res.push_str("&quot;"),
+            _ => push_html_escaped(res, c.encode_utf8(&mut [0; 4])),
+        }
+    }
+}
+
+/// Make it so new text is separated by an empty line
 fn blank_line(res: &mut String) {
     if !(res.is_empty() || res.ends_with("\n\n")) {
         at_newline(res);
@@ -524,7 +1498,7 @@ fn blank_line(res: &mut String) {
     }
 }
 
-#[derive(Copy, Clone, Default)]
+#[derive(Copy, Clone, Default, Eq, PartialEq)]
 struct Styles {
     mono: bool,
     bold: bool,
@@ -558,47 +1532,379 @@ impl From<Style> for Styles {
                 mono: false,
                 italic: false,
             },
+            Style::EnvVar => Styles {
+                bold: false,
+                mono: true,
+                italic: false,
+            },
         }
     }
 }
 
+impl Styles {
+    /// Styles used for manpages, metavariables are not monospaced there, environment
+    /// variables are conventionally bold and important text is bold italic
+    fn roff(style: Style) -> Self {
+        match style {
+            Style::Important => Styles {
+                bold: true,
+                italic: true,
+                ..Styles::default()
+            },
+            Style::Metavar => Styles {
+                italic: true,
+                ..Styles::default()
+            },
+            Style::EnvVar => Styles {
+                bold: true,
+                ..Styles::default()
+            },
+            _ => Styles::from(style),
+        }
+    }
+
+    /// Combine styles of a fragment with styles of an enclosing span
+    fn with(self, other: Self) -> Self {
+        Styles {
+            mono: self.mono || other.mono,
+            bold: self.bold || other.bold,
+            italic: self.italic || other.italic,
+        }
+    }
+
+    /// Font that renders given combination of styles in a manpage
+    ///
+    /// There's no monospaced bold italic font so emphasis takes priority
+    fn font(self) -> Font {
+        match (self.mono, self.bold, self.italic) {
+            (false, false, false) => Font::Roman,
+            (false, true, false) => Font::Bold,
+            (false, false, true) => Font::Italic,
+            (_, true, true) => Font::BoldItalic,
+            (true, false, false) => Font::Mono,
+            (true, true, false) => Font::MonoBold,
+            (true, false, true) => Font::MonoItalic,
+        }
+    }
+}
+
+/// Html tags used to render [`Styles`] in markdown, in the order they are opened
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum Tag {
+    Mono,
+    Bold,
+    Italic,
+}
+
+impl Tag {
+    const ALL: [Tag; 3] = [Tag::Mono, Tag::Bold, Tag::Italic];
+
+    fn is_set(self, styles: Styles) -> bool {
+        match self {
+            Tag::Mono => styles.mono,
+            Tag::Bold => styles.bold,
+            Tag::Italic => styles.italic,
+        }
+    }
+
+    fn open(self) -> &'static str {
+        match self {
+            Tag::Mono => "<tt>",
+            Tag::Bold => "<b>",
+            Tag::Italic => "<i>",
+        }
+    }
+
+    fn close(self) -> &'static str {
+        match self {
+            Tag::Mono => "</tt>",
+            Tag::Bold => "</b>",
+            Tag::Italic => "</i>",
+        }
+    }
+}
+
+/// Close and open html tags to get from currently `open` tags to `new` styles
+///
+/// Tags are kept properly nested: to close a tag all the tags opened after it are closed first,
+/// they will be reopened if still needed
+fn change_style(res: &mut String, open: &mut Vec<Tag>, new: Styles) {
+    if let Some(ix) = open.iter().position(|tag| !tag.is_set(new)) {
+        for tag in open.drain(ix..).rev() {
+            res.push_str(tag.close());
+        }
+    }
+    for tag in Tag::ALL.iter().copied() {
+        if tag.is_set(new) && !open.contains(&tag) {
+            res.push_str(tag.open());
+            open.push(tag);
+        }
+    }
+}
+
+/// Part of a manpage heading captured before the heading is written
+enum Captured {
+    /// Text in a given font
+    Text(Font, String),
+    /// Escape sequence in a given font, written as is
+    Escape(Font, &'static str),
+}
+
+impl Captured {
+    fn font(&self) -> Font {
+        match self {
+            Captured::Text(font, _) | Captured::Escape(font, _) => *font,
+        }
+    }
+}
+
+/// Font for a heading fragment with given styles
+fn heading_font(level: usize, mut styles: Styles) -> Font {
+    // deeper headings are emulated with bold text
+    styles.bold |= level > 2;
+    match styles.font() {
+        // .SH and .SS use their own font for regular text
+        Font::Roman => Font::Current,
+        font => font,
+    }
+}
+
+/// Write captured heading fragments as regular text
+fn write_captured<I>(roff: &mut Roff, fragments: I)
+where
+    I: IntoIterator<Item = Captured>,
+{
+    let mut text = Vec::new();
+    for fragment in fragments {
+        match fragment {
+            Captured::Text(font, payload) => text.push((font, payload)),
+            Captured::Escape(font, escape) => {
+                roff.text(text.drain(..));
+                if font == Font::Current {
+                    roff.escape(escape);
+                } else {
+                    roff.font(font).escape(escape).escape(RESTORE_FONT);
+                }
+            }
+        }
+    }
+    roff.text(text);
+}
+
+/// Options for [`render_to_manpage_with`](Doc::render_to_manpage_with)
+#[derive(Debug, Clone)]
+pub struct ManpageOptions {
+    uppercase_sections: bool,
+    glyphs: Glyphs,
+    dialect: Dialect,
+}
+
+impl Default for ManpageOptions {
+    fn default() -> Self {
+        Self {
+            uppercase_sections: true,
+            glyphs: Glyphs::Utf8,
+            dialect: Dialect::Groff,
+        }
+    }
+}
+
+impl ManpageOptions {
+    /// Chainable setter for converting section names to all caps, enabled by default
+    ///
+    /// Most of the manpages use all caps section names
+    pub fn uppercase_sections(&mut self, state: bool) -> &mut Self {
+        self.uppercase_sections = state;
+        self
+    }
+
+    /// Chainable setter for non-ASCII characters handling, [`Glyphs::Utf8`] by default
+    ///
+    /// ```rust
+    /// # use ::roff::{*, roff::Glyphs};
+    /// let mut doc = Doc::default();
+    /// doc.paragraph("Na\u{ef}ve \u{2014} but works");
+    /// let page = doc.render_to_manpage_with(
+    ///     "FOO",
+    ///     Section::General,
+    ///     &[],
+    ///     ManpageOptions::default().glyphs(Glyphs::Ascii),
+    /// );
+    /// assert!(page.contains("Na\\[u00EF]ve \\(em but works"));
+    /// ```
+    pub fn glyphs(&mut self, glyphs: Glyphs) -> &mut Self {
+        self.glyphs = glyphs;
+        self
+    }
+
+    /// Chainable setter for the roff implementation to target, [`Dialect::Groff`] by default
+    ///
+    /// ```rust
+    /// # use ::roff::{*, roff::Dialect};
+    /// let mut doc = Doc::default();
+    /// doc.paragraph(mono("ls"));
+    /// let page = doc.render_to_manpage_with(
+    ///     "FOO",
+    ///     Section::General,
+    ///     &[],
+    ///     ManpageOptions::default().dialect(Dialect::Mandoc),
+    /// );
+    /// assert_eq!(page, ".TH FOO 1\n.PP\n\\fBls\\fP");
+    /// ```
+    pub fn dialect(&mut self, dialect: Dialect) -> &mut Self {
+        self.dialect = dialect;
+        self
+    }
+}
+
+/// Options for [`render_to_markdown_with`](Doc::render_to_markdown_with)
+#[derive(Debug, Clone, Default)]
+pub struct MarkdownOptions {
+    table_of_contents: bool,
+}
+
+impl MarkdownOptions {
+    /// Chainable setter for inserting [`table of contents`](Doc::table_of_contents), disabled by
+    /// default
+    ///
+    /// Table of contents goes after the first section
+    pub fn table_of_contents(&mut self, state: bool) -> &mut Self {
+        self.table_of_contents = state;
+        self
+    }
+}
+
 impl Doc {
+    /// Create a table of contents from section and subsection headings
+    ///
+    /// Table of contents is a list of links to the headings with subsections nested
+    ///
+    /// ```rust
+    /// # use ::roff::*;
+    /// let mut doc = Doc::default();
+    /// doc.section("Usage").section("Options").subsection("Flags");
+    /// let toc = doc.table_of_contents().render_to_markdown();
+    /// let expected = "\
+    /// <ul>
+    /// <li><a href=\"#usage\">Usage</a></li>
+    /// <li><a href=\"#options\">Options</a>
+    ///
+    /// <ul>
+    /// <li><a href=\"#flags\">Flags</a></li></ul></li></ul>";
+    ///
+    /// assert_eq!(toc, expected);
+    /// ```
+    #[must_use]
+    pub fn table_of_contents(&self) -> Doc {
+        let headings = self.headings();
+        // sections with their subsections
+        let mut entries: Vec<(&HeadingInfo, Vec<&HeadingInfo>)> = Vec::new();
+        for heading in &headings {
+            match (heading.level, entries.last_mut()) {
+                (2, Some((_, children))) => children.push(heading),
+                (1, _) | (2, None) => entries.push((heading, Vec::new())),
+                _ => {}
+            }
+        }
+
+        let mut doc = Doc::default();
+        if entries.is_empty() {
+            return doc;
+        }
+        doc.ulist(|doc: &mut Doc| {
+            for (heading, children) in &entries {
+                doc.item(|doc: &mut Doc| {
+                    doc.link(&format!("#{}", heading.id), text(&heading.title));
+                    if !children.is_empty() {
+                        doc.ulist(|doc: &mut Doc| {
+                            for child in children {
+                                doc.item(link(&format!("#{}", child.id), text(&child.title)));
+                            }
+                        });
+                    }
+                });
+            }
+        });
+        doc
+    }
+
     /// Render semantic document into markdown
     // not quite markdown but encasing things in html block items makes it so
     // rustdoc avoids replacing -- to unicode dash - a nice side effect to have
     #[must_use]
-    #[allow(clippy::too_many_lines)] // not that many
     pub fn render_to_markdown(&self) -> String {
-        let mut res = String::new();
-        let mut cur_style = Styles::default();
+        self.render_to_markdown_with(&MarkdownOptions::default())
+    }
 
-        fn change_style(res: &mut String, cur: &mut Styles, new: Styles) {
-            if cur.italic && !new.italic {
-                res.push_str("</i>")
-            }
-            if cur.bold && !new.bold {
-                res.push_str("</b>")
-            }
-            if cur.mono && !new.mono {
-                res.push_str("</tt>")
-            }
-            if !cur.mono && new.mono {
-                res.push_str("<tt>")
-            }
-            if !cur.bold && new.bold {
-                res.push_str("<b>")
-            }
-            if !cur.italic && new.italic {
-                res.push_str("<i>")
+    /// Render semantic document into markdown using custom options
+    ///
+    /// ```rust
+    /// # use ::roff::*;
+    /// let mut doc = Doc::default();
+    /// doc.section("Name").paragraph("foo").section("Usage");
+    /// let doc = doc.render_to_markdown_with(MarkdownOptions::default().table_of_contents(true));
+    /// let expected = "\
+    /// ## <a id=\"name\"></a>Name\n\n\
+    /// <p>foo</p>\n\n\
+    /// <ul>\n\
+    /// <li><a href=\"#name\">Name</a></li>\n\
+    /// <li><a href=\"#usage\">Usage</a></li></ul>\n\n\
+    /// ## <a id=\"usage\"></a>Usage\n\n";
+    /// assert_eq!(doc, expected);
+    /// ```
+    #[must_use]
+    pub fn render_to_markdown_with(&self, options: &MarkdownOptions) -> String {
+        let headings = self.headings();
+        let toc = if options.table_of_contents {
+            Some(self.table_of_contents())
+        } else {
+            None
+        };
+        let (body, notes) = self.split_footnotes();
+        let mut res = body.write_markdown(&headings, toc);
+        if !notes.is_empty() {
+            blank_line(&mut res);
+            res.push_str("<ol class=\"footnotes\">");
+            for (ix, note) in notes.iter().enumerate() {
+                res.push_str(&format!(
+                    "\n<li id=\"fn-{0}\">{1} <a href=\"#fnref-{0}\">\u{21a9}</a></li>",
+                    ix + 1,
+                    note.write_markdown(&headings, None)
+                ));
             }
-            *cur = new;
+            res.push_str("</ol>");
         }
+        res
+    }
+
+    /// Render document body, footnotes should be split already
+    #[allow(clippy::too_many_lines)] // not that many
+    fn write_markdown(&self, headings: &[HeadingInfo], mut toc: Option<Doc>) -> String {
+        let mut res = String::new();
+        let mut open_tags = Vec::new();
+        // combined styles of enclosing spans
+        let mut spans: Vec<Styles> = Vec::new();
 
         // Items inside definition lists are encased in <dd> instead of <li>
         let mut is_dlist = false;
+        // Code is already monospaced and html special characters need escaping
+        let mut is_code = false;
+        // Prototypes are styled for manpages only so syntax highlighting can work
+        let mut is_prototype = false;
+        let mut whitespace = Whitespace::default();
+        let mut heading_ids = headings.iter().map(|h| h.id.as_str());
+        let mut sections = 0;
         for (meta, payload) in &self.0 {
-            if !matches!(meta, Sem::Style(_)) {
-                change_style(&mut res, &mut cur_style, Styles::default());
+            whitespace.track(*meta);
+            if !matches!(
+                meta,
+                Sem::Style(_)
+                    | Sem::LineBreak
+                    | Sem::Glyph(_)
+                    | Sem::InlineStart(Inline::Style(_))
+                    | Sem::InlineEnd(Inline::Style(_))
+            ) {
+                change_style(&mut res, &mut open_tags, Styles::default());
             }
             match meta {
                 Sem::BlockStart(block) => match block {
@@ -629,7 +1935,7 @@ impl Doc {
                         at_newline(&mut res);
                         res.push_str("<dt>");
                     }
-                    LogicalBlock::Paragraph => {
+                    LogicalBlock::Paragraph | LogicalBlock::Whatis => {
                         blank_line(&mut res);
                         res.push_str("<p>");
                     }
@@ -637,13 +1943,61 @@ impl Doc {
                         blank_line(&mut res);
                         res.push_str("<pre>");
                     }
-                    LogicalBlock::Section => {
+                    LogicalBlock::Code => {
+                        blank_line(&mut res);
+                        is_code = true;
+                        if payload.is_empty() {
+                            res.push_str("<pre><code>");
+                        } else {
+                            // language must stay a single class name
+                            let language = payload.replace(char::is_whitespace, "-");
+                            res.push_str("<pre><code class=\"language-");
+                            push_attr_escaped(&mut res, &language);
+                            res.push_str("\">");
+                        }
+                    }
+                    LogicalBlock::Example => {
+                        blank_line(&mut res);
+                        is_code = true;
+                        res.push_str("<pre><code class=\"language-console\">");
+                    }
+                    LogicalBlock::Prototype => {
                         blank_line(&mut res);
-                        res.push_str("# ");
+                        is_code = true;
+                        is_prototype = true;
+                        res.push_str("<pre><code class=\"language-c\">");
+                    }
+                    LogicalBlock::Heading(level) => {
+                        if *level == 1 {
+                            sections += 1;
+                            if sections == 2 {
+                                if let Some(toc) = toc.take() {
+                                    blank_line(&mut res);
+                                    res.push_str(&toc.render_to_markdown());
+                                }
+                            }
+                        }
+                        blank_line(&mut res);
+                        // markdown only has six levels of headings
+                        res.push_str(&"#".repeat((*level).clamp(1, 6)));
+                        res.push(' ');
+                        if let Some(id) = heading_ids.next() {
+                            res.push_str("<a id=\"");
+                            push_attr_escaped(&mut res, id);
+                            res.push_str("\"></a>");
+                        }
                     }
-                    LogicalBlock::Subsection => {
+                    LogicalBlock::Indent(_) => {
                         blank_line(&mut res);
-                        res.push_str("## ");
+                        res.push_str("<blockquote>");
+                    }
+                    LogicalBlock::Admonition(kind) => {
+                        blank_line(&mut res);
+                        res.push_str(&format!(
+                            "<div class=\"{}\"><b>{}:</b>",
+                            kind.class(),
+                            kind.label()
+                        ));
                     }
                 },
                 Sem::BlockEnd(block) => match block {
@@ -658,19 +2012,95 @@ impl Doc {
                         }
                     }
                     LogicalBlock::ListKey => res.push_str("</dt>"),
-                    LogicalBlock::Paragraph => res.push_str("</p>"),
+                    LogicalBlock::Paragraph | LogicalBlock::Whatis => res.push_str("</p>"),
                     LogicalBlock::Pre => res.push_str("</pre>"),
-                    LogicalBlock::Section | LogicalBlock::Subsection => {
+                    LogicalBlock::Code | LogicalBlock::Example | LogicalBlock::Prototype => {
+                        is_code = false;
+                        is_prototype = false;
+                        res.push_str("</code></pre>");
+                    }
+                    LogicalBlock::Heading(_) => {
                         blank_line(&mut res);
                     }
+                    LogicalBlock::Admonition(_) => {
+                        at_newline(&mut res);
+                        res.push_str("</div>");
+                    }
+                    LogicalBlock::Indent(_) => {
+                        at_newline(&mut res);
+                        res.push_str("</blockquote>");
+                    }
                 },
+                Sem::InlineStart(Inline::Style(style)) => {
+                    let outer = spans.last().copied().unwrap_or_default();
+                    spans.push(outer.with(Styles::from(*style)));
+                }
+                Sem::InlineStart(Inline::Link) => {
+                    res.push_str("<a href=\"");
+                    push_attr_escaped(&mut res, payload);
+                    res.push_str("\">");
+                    spans.push(spans.last().copied().unwrap_or_default());
+                }
+                Sem::InlineStart(Inline::Footnote) => {
+                    res.push_str(&format!(
+                        "<sup id=\"fnref-{0}\"><a href=\"#fn-{0}\">{0}</a></sup>",
+                        payload
+                    ));
+                    spans.push(spans.last().copied().unwrap_or_default());
+                }
+                Sem::InlineEnd(inline) => {
+                    spans.pop();
+                    if *inline == Inline::Link {
+                        res.push_str("</a>");
+                    }
+                }
+                Sem::LineBreak => {
+                    // styles of the previous fragment don't carry over the break
+                    let outer = spans.last().copied().unwrap_or_default();
+                    change_style(&mut res, &mut open_tags, outer);
+                    res.push_str("<br>");
+                }
+                Sem::Glyph(glyph) => {
+                    let mut styles = spans.last().copied().unwrap_or_default();
+                    if is_prototype {
+                        styles = Styles::default();
+                    }
+                    styles.mono &= !is_code;
+                    change_style(&mut res, &mut open_tags, styles);
+                    res.push_str(glyph.html());
+                }
+                Sem::Reference => {
+                    let title =
+                        Self::find_heading(headings, payload).map_or(payload, |h| h.title.as_str());
+                    res.push_str("<a href=\"#");
+                    push_attr_escaped(&mut res, payload);
+                    res.push_str("\">");
+                    push_html_escaped(&mut res, title);
+                    res.push_str("</a>");
+                }
                 Sem::Style(style) => {
-                    change_style(&mut res, &mut cur_style, Styles::from(*style));
-                    res.push_str(payload);
+                    let outer = spans.last().copied().unwrap_or_default();
+                    let mut styles = outer.with(Styles::from(*style));
+                    let payload = whitespace.apply(*style, payload);
+                    if is_prototype {
+                        styles = Styles::default();
+                    }
+                    if is_code {
+                        styles.mono = false;
+                        change_style(&mut res, &mut open_tags, styles);
+                        push_html_escaped(&mut res, &payload);
+                    } else {
+                        change_style(&mut res, &mut open_tags, styles);
+                        res.push_str(&payload);
+                    }
                 }
             }
         }
-        change_style(&mut res, &mut cur_style, Styles::default());
+        change_style(&mut res, &mut open_tags, Styles::default());
+        if let Some(toc) = toc {
+            blank_line(&mut res);
+            res.push_str(&toc.render_to_markdown());
+        }
         res
     }
 
@@ -691,17 +2121,74 @@ impl Doc {
     /// `extra` values should not be empty, but it's OK to have less than 3 items
     #[must_use]
     pub fn render_to_manpage(&self, title: &str, section: Section, extra: &[&str]) -> String {
-        let mut roff = crate::roff::Roff::default();
+        self.render_to_manpage_with(title, section, extra, &ManpageOptions::default())
+    }
 
-        roff.control(
-            "TH",
-            [title, section.as_str()].iter().chain(extra.iter().take(3)),
-        );
+    /// Render semantic document into a manpage using custom options
+    ///
+    /// See [`render_to_manpage`](Self::render_to_manpage) for details
+    ///
+    /// ```rust
+    /// # use ::roff::*;
+    /// let mut doc = Doc::default();
+    /// doc.section("Description");
+    /// let page = doc.render_to_manpage_with(
+    ///     "FOO",
+    ///     Section::General,
+    ///     &[],
+    ///     ManpageOptions::default().uppercase_sections(false),
+    /// );
+    /// assert!(page.ends_with(".SH Description\n"));
+    /// ```
+    #[must_use]
+    #[allow(clippy::too_many_lines)]
+    pub fn render_to_manpage_with(
+        &self,
+        title: &str,
+        section: Section,
+        extra: &[&str],
+        options: &ManpageOptions,
+    ) -> String {
+        let mut roff = Roff::default();
+
+        roff.man(Macro::Th {
+            title,
+            section,
+            date: extra.first().copied(),
+            source: extra.get(1).copied(),
+            manual: extra.get(2).copied(),
+        });
+
+        let headings = self.headings();
+        let (body, notes) = self.split_footnotes();
+        body.write_manpage(&mut roff, &headings, options);
+        if !notes.is_empty() {
+            let name = if options.uppercase_sections {
+                "NOTES"
+            } else {
+                "Notes"
+            };
+            roff.man(Macro::Sh(Some(name)));
+            for (ix, note) in notes.iter().enumerate() {
+                roff.man(Macro::Ip(&format!("{:2}.", ix + 1), Some(4)));
+                note.write_manpage(&mut roff, &headings, options);
+            }
+        }
 
-        // sections and subsections are implemented with .SH and .SS
-        // control messages and it is easier to provide them right away
-        // We also strip styling from them and change sections to all caps
-        let mut capture = (String::new(), false);
+        roff.render(
+            RenderOptions::default()
+                .glyphs(options.glyphs)
+                .dialect(options.dialect),
+        )
+    }
+
+    /// Render document body, footnotes should be split already
+    #[allow(clippy::too_many_lines)] // not that many
+    fn write_manpage(&self, roff: &mut Roff, headings: &[HeadingInfo], options: &ManpageOptions) {
+        // sections and subsections are implemented with .SH and .SS control messages, unstyled
+        // ones are easier to read when passed as arguments so headings are captured first
+        let mut heading = None;
+        let mut captured: Vec<Captured> = Vec::new();
         #[derive(Clone, Copy)]
         enum ListKind {
             Def,
@@ -709,18 +2196,41 @@ impl Doc {
             Ul,
         }
         let mut kind = ListKind::Def;
+        // combined styles of enclosing spans and targets of enclosing links
+        let mut spans: Vec<Styles> = Vec::new();
+        let mut links = Vec::new();
+        // .EX sets a monospaced font, regular text should keep it
+        let mut is_code = false;
+        // prototypes are written as is in no-fill mode, with bold text and italic parameters
+        let mut is_prototype = false;
+        let mut prototype_font = None;
+        // NAME section is parsed by indexing tools which don't expect any font changes
+        let mut is_whatis = false;
+        let mut whitespace = Whitespace::default();
         for (meta, payload) in &self.0 {
+            whitespace.track(*meta);
             match meta {
                 Sem::BlockStart(b) => match b {
-                    LogicalBlock::Section | LogicalBlock::Subsection => {
-                        capture.1 = true;
+                    LogicalBlock::Heading(level) => {
+                        heading = Some(*level);
                     }
                     LogicalBlock::Pre => {
                         // .nf - turn off fill mode
-                        roff.control0("PP").control0("nf").strip_newlines(false);
+                        roff.man(Macro::Pp).control0("nf").strip_newlines(false);
+                    }
+                    LogicalBlock::Code | LogicalBlock::Example => {
+                        is_code = true;
+                        roff.man(Macro::Pp).man(Macro::Ex).strip_newlines(false);
+                    }
+                    LogicalBlock::Prototype => {
+                        is_prototype = true;
+                        roff.man(Macro::Pp).control0("nf").strip_newlines(false);
                     }
                     LogicalBlock::Paragraph => {
-                        roff.control0("PP");
+                        roff.man(Macro::Pp);
+                    }
+                    LogicalBlock::Whatis => {
+                        is_whatis = true;
                     }
                     LogicalBlock::UnnumberedList => {
                         kind = ListKind::Ul;
@@ -744,25 +2254,69 @@ impl Doc {
                         }
                     },
                     LogicalBlock::ListKey => {
-                        roff.control0("TP").strip_newlines(true);
+                        roff.man(Macro::Tp(None)).strip_newlines(true);
+                    }
+                    LogicalBlock::Indent(None) => {
+                        roff.man(Macro::Rs(None));
+                    }
+                    LogicalBlock::Indent(Some(width)) => {
+                        roff.man(Macro::Rs(Some(*width)));
+                    }
+                    LogicalBlock::Admonition(kind) => {
+                        roff.man(Macro::Rs(None))
+                            .man(Macro::Pp)
+                            .text([(Font::Bold, format!("{}:", kind.label().to_uppercase()))]);
                     }
                 },
                 Sem::BlockEnd(b) => match b {
                     LogicalBlock::Paragraph => {}
+                    LogicalBlock::Whatis => {
+                        // following inline content must not end up on the NAME line
+                        is_whatis = false;
+                        roff.roff_linebreak();
+                    }
                     LogicalBlock::Pre => {
                         // .fi - restore fill mode
                         roff.control0("fi").strip_newlines(true);
                     }
-
-                    LogicalBlock::Section => {
-                        capture.1 = false;
-                        roff.control("SH", [capture.0.to_uppercase()]);
-                        capture.0.clear();
+                    LogicalBlock::Code | LogicalBlock::Example => {
+                        is_code = false;
+                        roff.man(Macro::Ee).strip_newlines(true);
+                    }
+                    LogicalBlock::Prototype => {
+                        is_prototype = false;
+                        if prototype_font.take().is_some() {
+                            roff.escape(RESTORE_FONT);
+                        }
+                        roff.control0("fi").strip_newlines(true);
                     }
-                    LogicalBlock::Subsection => {
-                        capture.1 = false;
-                        roff.control("SS", [&capture.0]);
-                        capture.0.clear();
+                    LogicalBlock::Heading(level) => {
+                        heading = None;
+                        if *level > 2 {
+                            roff.man(Macro::Pp);
+                            write_captured(roff, captured.drain(..));
+                            continue;
+                        }
+                        let plain = captured.iter().all(|c| c.font() == Font::Current);
+                        // macro arguments keep escape sequences, text backslashes must be escaped
+                        let heading_name = plain.then(|| {
+                            captured
+                                .drain(..)
+                                .map(|c| match c {
+                                    Captured::Text(_, s) => s.replace('\\', "\\e"),
+                                    Captured::Escape(_, e) => e.to_string(),
+                                })
+                                .collect::<String>()
+                        });
+                        // the name is escaped already, Macro::Sh would escape it again
+                        let name = if *level == 1 { "SH" } else { "SS" };
+                        roff.control(name, heading_name.as_deref().map(Arg::Raw));
+                        if !plain {
+                            let strip = roff.strip_newlines;
+                            roff.strip_newlines(true);
+                            write_captured(roff, captured.drain(..));
+                            roff.roff_linebreak().strip_newlines(strip);
+                        }
                     }
                     LogicalBlock::UnnumberedList | LogicalBlock::NumberedList => {
 
@@ -770,22 +2324,129 @@ impl Doc {
                     }
                     LogicalBlock::DefinitionList => {}
                     LogicalBlock::ListItem => {
-                        roff.control0("PP").strip_newlines(false);
+                        roff.man(Macro::Pp).strip_newlines(false);
                     }
                     LogicalBlock::ListKey => {
                         roff.roff_linebreak().strip_newlines(false);
                     }
+                    LogicalBlock::Admonition(_) | LogicalBlock::Indent(_) => {
+                        roff.man(Macro::Re);
+                    }
                 },
-                Sem::Style(_) if capture.1 => {
-                    capture.0.push_str(payload);
+                Sem::InlineStart(inline) => {
+                    let outer = spans.last().copied().unwrap_or_default();
+                    match inline {
+                        Inline::Style(s) => spans.push(outer.with(Styles::roff(*s))),
+                        Inline::Link => {
+                            spans.push(outer);
+                            links.push(payload);
+                        }
+                        Inline::Footnote => {
+                            spans.push(outer);
+                            let mark = format!("[{}]", payload);
+                            if heading.is_some() {
+                                captured.push(Captured::Text(Font::Current, mark));
+                            } else {
+                                roff.text([(Font::Roman, mark)]);
+                            }
+                        }
+                    }
+                }
+                Sem::InlineEnd(inline) => {
+                    spans.pop();
+                    if *inline == Inline::Link {
+                        if let Some(target) = links.pop() {
+                            if heading.is_some() {
+                                let target = format!(" <{}>", target);
+                                captured.push(Captured::Text(Font::Current, target));
+                            } else {
+                                roff.text([(Font::Roman, format!(" <{}>", target))]);
+                            }
+                        }
+                    }
+                }
+                Sem::LineBreak if heading.is_some() => {
+                    captured.push(Captured::Text(Font::Current, String::from(" ")));
+                }
+                Sem::LineBreak => {
+                    roff.control0("br");
+                }
+                Sem::Glyph(glyph) if is_prototype => {
+                    roff.escape(glyph.roff(options.dialect));
+                }
+                Sem::Glyph(glyph) if heading.is_some() => {
+                    let level = heading.unwrap_or_default();
+                    let font = heading_font(level, spans.last().copied().unwrap_or_default());
+                    captured.push(Captured::Escape(font, glyph.roff(options.dialect)));
+                }
+                Sem::Glyph(glyph) => {
+                    let font = spans.last().copied().unwrap_or_default().font();
+                    if font == Font::Roman || font == Font::Current {
+                        roff.escape(glyph.roff(options.dialect));
+                    } else {
+                        roff.font(font)
+                            .escape(glyph.roff(options.dialect))
+                            .escape(RESTORE_FONT);
+                    }
+                }
+                Sem::Reference => {
+                    let title = match Self::find_heading(headings, payload) {
+                        Some(h) if h.level == 1 && options.uppercase_sections => {
+                            h.title.to_uppercase()
+                        }
+                        Some(h) => h.title.clone(),
+                        None => payload.to_string(),
+                    };
+                    if let Some(level) = heading {
+                        let outer = spans.last().copied().unwrap_or_default();
+                        let font = heading_font(level, outer);
+                        captured.push(Captured::Escape(font, "\\(lq"));
+                        captured.push(Captured::Text(font, title));
+                        captured.push(Captured::Escape(font, "\\(rq"));
+                    } else {
+                        let outer = spans.last().copied().unwrap_or_default();
+                        roff.escape("\\(lq")
+                            .text([(outer.font(), title)])
+                            .escape("\\(rq");
+                    }
+                }
+                Sem::Style(s) if is_prototype => {
+                    let font = if *s == Style::Metavar {
+                        Font::Italic
+                    } else {
+                        Font::Bold
+                    };
+                    if prototype_font != Some(font) {
+                        roff.font(font);
+                        prototype_font = Some(font);
+                    }
+                    roff.plaintext(payload);
+                }
+                Sem::Style(s) if is_whatis => {
+                    roff.text([(Font::Current, whitespace.apply(*s, payload))]);
+                }
+                Sem::Style(s) if heading.is_some() => {
+                    let level = heading.unwrap_or_default();
+                    let outer = spans.last().copied().unwrap_or_default();
+                    let font = heading_font(level, outer.with(Styles::roff(*s)));
+                    let mut payload = whitespace.apply(*s, payload).into_owned();
+                    // literal values such as option names or metavariables keep their case
+                    let is_prose = matches!(s, Style::Text | Style::Important);
+                    if level == 1 && options.uppercase_sections && is_prose {
+                        payload = payload.to_uppercase();
+                    }
+                    captured.push(Captured::Text(font, payload));
                 }
                 Sem::Style(s) => {
-                    roff.text([(s.font(), payload)]);
+                    let outer = spans.last().copied().unwrap_or_default();
+                    let mut font = outer.with(Styles::roff(*s)).font();
+                    if is_code && font == Font::Roman {
+                        font = Font::Current;
+                    }
+                    roff.text([(font, whitespace.apply(*s, payload))]);
                 }
             }
         }
-
-        roff.render(Apostrophes::Handle)
     }
 }
 
@@ -794,3 +2455,333 @@ fn push_slice() {
     let mut doc = Doc::default();
     doc.push([mono("hello"), mono("world")]);
 }
+
+#[test]
+fn nested_spans_markdown() {
+    let mut doc = Doc::default();
+    doc.span(
+        Style::Important,
+        [text("run "), literal("--force"), text(" with care")],
+    )
+    .text(" or ")
+    .link("https://example.com", [text("ask "), metavar("USER")]);
+    let expected = "<b>run <tt>--force</tt> with care</b> or \
+                    <a href=\"https://example.com\">ask <tt><i>USER</i></tt></a>";
+    assert_eq!(doc.render_to_markdown(), expected);
+}
+
+#[test]
+fn nested_spans_manpage() {
+    let mut doc = Doc::default();
+    doc.span(Style::Important, [text("pass "), metavar("FILE")]);
+    let page = doc.render_to_manpage("X", Section::General, &[]);
+    assert!(page.ends_with("\\f(BIpass \\fP\\f(BIFILE\\fP"), "{}", page);
+}
+
+#[test]
+fn prototype_manpage() {
+    let mut doc = Doc::default();
+    doc.prototype(|doc: &mut Doc| {
+        doc.literal("int f(int ")
+            .metavar("a")
+            .literal(", int ")
+            .metavar("b")
+            .literal(", int ")
+            .metavar("c")
+            .literal(", int ")
+            .metavar("d")
+            .literal(");\n.end");
+    });
+    let page = doc.render_to_manpage("X", Section::General, &[]);
+    let expected = ".PP\n.nf\n\\fBint f(int \\fIa\\fB, int \\fIb\\fB, int \\fIc\\fB, \
+                    int \\fId\\fB);\n\\&.end\\fP\n.fi\n";
+    assert!(page.ends_with(expected), "{}", page);
+}
+
+#[test]
+fn admonition_manpage() {
+    let mut doc = Doc::default();
+    doc.admonition(Admonition::Note, |doc: &mut Doc| {
+        doc.paragraph(text("Mind the gap"));
+    });
+    let page = doc.render_to_manpage("X", Section::General, &[]);
+    assert!(
+        page.ends_with(".RS\n.PP\n\\fBNOTE:\\fP\n.PP\n\\fRMind the gap\\fP\n.RE\n"),
+        "{}",
+        page
+    );
+}
+
+#[test]
+fn example_manpage() {
+    let mut doc = Doc::default();
+    doc.example("$", "ls -l", "total 0");
+    let page = doc.render_to_manpage("X", Section::General, &[]);
+    let expected = r#".ie \n(.g .ds Aq \(aq
+.el .ds Aq '
+.TH X 1
+.if !d EX \{\
+.de EX
+.nf
+.ft CR
+..
+.de EE
+.ft R
+.fi
+..
+.\}
+.PP
+.EX
+$ \f(CBls \-l\fP
+total 0
+.EE
+"#;
+    assert_eq!(page, expected);
+}
+
+#[test]
+fn example_manpage_dialects() {
+    let mut doc = Doc::default();
+    doc.example("$", "echo 'hi'", "hi");
+    let render = |dialect| {
+        doc.render_to_manpage_with(
+            "X",
+            Section::General,
+            &[],
+            ManpageOptions::default().dialect(dialect),
+        )
+    };
+    let body = ".PP\n.EX\n$ \\fBecho \\(aqhi\\(aq\\fP\nhi\n.EE\n";
+    assert_eq!(render(Dialect::Mandoc), format!(".TH X 1\n{}", body));
+    let body = ".PP\n.EX\n$ \\fBecho 'hi'\\fP\nhi\n.EE\n";
+    assert_eq!(render(Dialect::Plan9), format!(".TH X 1\n{}", body));
+    assert_eq!(
+        render(Dialect::Legacy),
+        format!(
+            ".TH X 1\n.de EX\n.nf\n.ft CW\n..\n.de EE\n.ft R\n.fi\n..\n{}",
+            body
+        )
+    );
+}
+
+#[test]
+fn glyphs_by_dialect() {
+    let mut doc = Doc::default();
+    doc.glyph(Glyph::LeftSingleQuote)
+        .text("hi")
+        .glyph(Glyph::RightSingleQuote);
+    let render = |dialect| {
+        doc.render_to_manpage_with(
+            "X",
+            Section::General,
+            &[],
+            ManpageOptions::default().dialect(dialect),
+        )
+    };
+    assert!(render(Dialect::Groff).ends_with("\\(oq\\fRhi\\fP\\(cq"));
+    assert!(render(Dialect::Legacy).ends_with("`\\fRhi\\fP\\&'"));
+}
+
+#[test]
+fn heading_footnote() {
+    let mut doc = Doc::default();
+    doc.heading(1, |doc: &mut Doc| {
+        doc.text("Opts").footnote(text("n"));
+    });
+    assert!(doc.has_section("Opts"));
+    let markdown = doc.render_to_markdown();
+    assert!(
+        markdown.starts_with("# <a id=\"opts\"></a>Opts<sup"),
+        "{}",
+        markdown
+    );
+}
+
+#[test]
+fn nested_footnotes() {
+    let mut doc = Doc::default();
+    doc.paragraph(|doc: &mut Doc| {
+        doc.text("a")
+            .footnote(|doc: &mut Doc| {
+                doc.text("b").footnote(text("c"));
+            })
+            .text("d")
+            .footnote(text("e"));
+    });
+    let expected = "<p>a<sup id=\"fnref-1\"><a href=\"#fn-1\">1</a></sup>d\
+                    <sup id=\"fnref-2\"><a href=\"#fn-2\">2</a></sup></p>\n\n\
+                    <ol class=\"footnotes\">\n\
+                    <li id=\"fn-1\">b<sup id=\"fnref-3\"><a href=\"#fn-3\">3</a></sup> \
+                    <a href=\"#fnref-1\">\u{21a9}</a></li>\n\
+                    <li id=\"fn-2\">e <a href=\"#fnref-2\">\u{21a9}</a></li>\n\
+                    <li id=\"fn-3\">c <a href=\"#fnref-3\">\u{21a9}</a></li></ol>";
+    assert_eq!(doc.render_to_markdown(), expected);
+
+    let page = doc.render_to_manpage("X", Section::General, &[]);
+    let expected = ".SH NOTES\n.IP \" 1.\" 4\n\\fRb\\fP\\fR[3]\\fP\n\
+                    .IP \" 2.\" 4\n\\fRe\\fP\n.IP \" 3.\" 4\n\\fRc\\fP";
+    assert!(page.ends_with(expected), "{}", page);
+}
+
+#[test]
+fn env_vars() {
+    let mut doc = Doc::default();
+    doc.paragraph([text("set "), env_var("HOME"), text(" first")]);
+    assert_eq!(doc.render_to_markdown(), "<p>set <tt>HOME</tt> first</p>");
+    let page = doc.render_to_manpage("X", Section::General, &[]);
+    assert!(
+        page.ends_with(".PP\n\\fRset \\fP\\fBHOME\\fP\\fR first\\fP"),
+        "{}",
+        page
+    );
+}
+
+#[test]
+fn nested_indent() {
+    let mut doc = Doc::default();
+    doc.indent(Some(4), |doc: &mut Doc| {
+        doc.paragraph("outer").indent(None, |doc: &mut Doc| {
+            doc.paragraph("inner");
+        });
+    });
+    let expected = "<blockquote>\n\n<p>outer</p>\n\n<blockquote>\n\n<p>inner</p>\n</blockquote>\n</blockquote>";
+    assert_eq!(doc.render_to_markdown(), expected);
+
+    let page = doc.render_to_manpage("X", Section::General, &[]);
+    let expected = ".RS 4n\n.PP\n\\fRouter\\fP\n.RS\n.PP\n\\fRinner\\fP\n.RE\n.RE\n";
+    assert!(page.ends_with(expected), "{}", page);
+}
+
+#[test]
+fn whitespace_and_line_breaks() {
+    let mut doc = Doc::default();
+    doc.paragraph(|doc: &mut Doc| {
+        doc.text("  first \n")
+            .text(" line")
+            .line_break()
+            .text(" second")
+            .mono("  line");
+    });
+    assert_eq!(
+        doc.render_to_markdown(),
+        "<p>first line<br>second<tt>  line</tt></p>"
+    );
+    let page = doc.render_to_manpage("X", Section::General, &[]);
+    let expected = ".PP\n\\fRfirst line\\fP\n.br\n\\fRsecond\\fP\\f(CR  line\\fP";
+    assert!(page.ends_with(expected), "{}", page);
+}
+
+#[test]
+fn styled_headings_manpage() {
+    let mut doc = Doc::default();
+    doc.heading(1, [text("The "), literal("--force")])
+        .heading(2, "plain")
+        .heading(3, [text("deep "), metavar("FILE")]);
+    let page = doc.render_to_manpage("X", Section::General, &[]);
+    let expected = ".SH\nTHE \\f(CB\\-\\-force\\fP\n.SS plain\n.PP\n\\fBdeep \\f(BIFILE\\fP";
+    assert!(page.ends_with(expected), "{}", page);
+}
+
+#[test]
+fn code_language_markdown() {
+    let mut doc = Doc::default();
+    doc.code(Some("c\"><b>x"), "")
+        .code(Some("shell session"), "");
+    let expected = "<pre><code class=\"language-c&quot;&gt;&lt;b&gt;x\"></code></pre>\n\n\
+                    <pre><code class=\"language-shell-session\"></code></pre>";
+    assert_eq!(doc.render_to_markdown(), expected);
+}
+
+#[test]
+fn deep_headings_markdown() {
+    let mut doc = Doc::default();
+    doc.heading(8, "Deep");
+    assert_eq!(
+        doc.render_to_markdown(),
+        "###### <a id=\"deep\"></a>Deep\n\n"
+    );
+}
+
+#[test]
+fn heading_ids_and_references() {
+    let mut doc = Doc::default();
+    doc.section("Options")
+        .subsection("Options")
+        .heading_with_id(2, "options-1", "Other")
+        .paragraph(|doc: &mut Doc| {
+            doc.text("see ").reference("options-2");
+        });
+    let ids = doc.headings().into_iter().map(|h| h.id).collect::<Vec<_>>();
+    assert_eq!(ids, ["options", "options-2", "options-1"]);
+
+    let page = doc.render_to_manpage("X", Section::General, &[]);
+    assert!(
+        page.ends_with("\\fRsee \\fP\\(lq\\fROptions\\fP\\(rq"),
+        "{}",
+        page
+    );
+}
+
+#[test]
+fn markdown_escapes_links_and_references() {
+    let mut doc = Doc::default();
+    doc.heading_with_id(1, "a\"b", "Tom & Jerry")
+        .paragraph(|doc: &mut Doc| {
+            doc.reference("a\"b")
+                .link("https://example.com/?q=\"x\"&y", text("link"));
+        });
+    let expected = "# <a id=\"a&quot;b\"></a>Tom & Jerry\n\n\
+                    <p><a href=\"#a&quot;b\">Tom &amp; Jerry</a>\
+                    <a href=\"https://example.com/?q=&quot;x&quot;&amp;y\">link</a></p>";
+    assert_eq!(doc.render_to_markdown(), expected);
+}
+
+#[test]
+fn heading_reference_manpage() {
+    let mut doc = Doc::default();
+    doc.section("Usage")
+        .heading(2, |doc: &mut Doc| {
+            doc.text("See ").reference("usage");
+        })
+        .heading(3, |doc: &mut Doc| {
+            doc.text("Also ").reference("usage");
+        });
+    let page = doc.render_to_manpage("X", Section::General, &[]);
+    let expected = ".SH USAGE\n.SS \"See \\(lqUSAGE\\(rq\"\n\
+                    .PP\n\\fBAlso \\fP\\fB\\(lq\\fP\\fBUSAGE\\fP\\fB\\(rq\\fP";
+    assert!(page.ends_with(expected), "{}", page);
+}
+
+#[test]
+fn heading_backslash_manpage() {
+    let mut doc = Doc::default();
+    doc.section("C:\\(bu");
+    let page = doc.render_to_manpage("X", Section::General, &[]);
+    assert!(page.ends_with(".SH C:\\e(BU\n"), "{}", page);
+}
+
+#[test]
+fn title_backslash_manpage() {
+    let page = Doc::default().render_to_manpage("A \\fB", Section::General, &[]);
+    assert!(page.contains("\n.TH \"A \\efB\" 1\n"), "{}", page);
+}
+
+#[test]
+fn glyphs() {
+    let mut doc = Doc::default();
+    doc.heading(1, |doc: &mut Doc| {
+        doc.text("Copyright").glyph(Glyph::NonBreakingSpace);
+    });
+    doc.paragraph(|doc: &mut Doc| {
+        doc.push(span(Style::Important, |doc: &mut Doc| {
+            doc.text("one").glyph(Glyph::Ellipsis).text("two");
+        }));
+    });
+    assert_eq!(
+        doc.render_to_markdown(),
+        "# <a id=\"copyright\"></a>Copyright&nbsp;\n\n<p><b>one&hellip;two</b></p>"
+    );
+    assert!(doc
+        .render_to_manpage("FOO", Section::General, &[])
+        .ends_with(".SH COPYRIGHT\\~\n.PP\n\\f(BIone\\fP\\f(BI\\&.\\|.\\|.\\fP\\f(BItwo\\fP"));
+}
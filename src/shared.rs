@@ -1,15 +1,18 @@
-use crate::roff::Font;
-
 /// Style and meaning of a particular snippet of text
+///
+/// New styles can be added in minor releases
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[non_exhaustive]
 pub enum Style {
     /// Encased string that is or a part of an option name inclusive with dashes
     /// `-f` or `--foo`
+    ///
     /// Something that user needs to type literally
     Literal,
 
     /// Metavariable part
     /// - FOO here: --foo FOO
+    ///
     /// Something that user needs to replace with their own input
     Metavar,
 
@@ -21,18 +24,9 @@ pub enum Style {
 
     /// Highlighted part of a text
     Important,
-}
 
-impl Style {
-    pub(crate) fn font(self) -> Font {
-        match self {
-            Style::Metavar => Font::Italic,
-            Style::Literal => Font::MonoBold,
-            Style::Text => Font::Roman,
-            Style::Important => Font::BoldItalic,
-            Style::Mono => Font::Mono,
-        }
-    }
+    /// Name of an environment variable such as `HOME`
+    EnvVar,
 }
 
 #[derive(Debug, Clone, Copy)]
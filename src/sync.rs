@@ -0,0 +1,480 @@
+//! Keeping generated files up to date
+
+use crate::diff::unified;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Options for [`write_updated_with`] and [`sync_dir_with`]
+#[derive(Debug, Clone, Default)]
+pub struct WriteOptions {
+    atomic: bool,
+    check_only: bool,
+}
+
+impl WriteOptions {
+    /// Write into a temporary file in the same directory first and rename it over the target
+    /// so readers never see a partially written file, permissions of an existing file are
+    /// preserved
+    ///
+    /// Default is `false`
+    pub fn atomic(&mut self, state: bool) -> &mut Self {
+        self.atomic = state;
+        self
+    }
+
+    /// Only check if files are up to date and report the differences without touching them
+    ///
+    /// Default is `false`
+    pub fn check_only(&mut self, state: bool) -> &mut Self {
+        self.check_only = state;
+        self
+    }
+
+    /// Write files only if environment variable `name` is set to anything but an empty string
+    /// or `0`, only check them otherwise
+    ///
+    /// This is useful for tests: CI fails if checked in files are outdated and running tests
+    /// with something like `BLESS=1` updates them
+    pub fn bless_from_env(&mut self, name: &str) -> &mut Self {
+        let bless = std::env::var_os(name).map_or(false, |val| !val.is_empty() && val != "0");
+        self.check_only(!bless)
+    }
+}
+
+/// Number of temporary files created by this process so far, keeps their names unique
+static TEMP_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// Replace file contents using a temporary file in the same directory
+fn write_atomic(path: &Path, value: &[u8]) -> io::Result<()> {
+    use std::io::Write;
+    let name = path.file_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a file path", path.display()),
+        )
+    })?;
+    let tmp = path.with_file_name(format!(
+        ".{}.{}.{}.tmp",
+        name.to_string_lossy(),
+        std::process::id(),
+        TEMP_FILES.fetch_add(1, Ordering::Relaxed)
+    ));
+    let res = (|| {
+        let mut file = std::fs::File::create(&tmp)?;
+        file.write_all(value)?;
+        file.sync_all()?;
+        match std::fs::metadata(path) {
+            Ok(meta) => std::fs::set_permissions(&tmp, meta.permissions())?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+        std::fs::rename(&tmp, path)
+    })();
+    if res.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    res
+}
+
+/// Update file contents if needed, returns previous contents if it was needed
+fn update(path: &Path, value: &[u8], options: &WriteOptions) -> io::Result<Option<Vec<u8>>> {
+    let current = match std::fs::read(path) {
+        Ok(current) if current == value => return Ok(None),
+        Ok(current) => current,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(err) => return Err(err),
+    };
+    if !options.check_only {
+        if options.atomic {
+            write_atomic(path, value)?;
+        } else {
+            std::fs::write(path, value)?;
+        }
+    }
+    Ok(Some(current))
+}
+
+/// Update file contents if needed and return if it was needed
+///
+/// A missing file is created, but with empty contents this doesn't count as a change
+///
+/// # Example
+///
+/// One way to use this function would be to make a test like this so CI would fail if files
+/// in the repository are outdated.
+/// ```no_run
+/// # /*
+/// #[test]
+/// fn update_documentation() {
+/// # */
+/// # use ::roff::write_updated;
+///     // create some document
+///     let doc = "hello world";
+///
+///     // write file, fail the test so CI fails if new documentation
+///     // not checked in so repository is up to date if CI passes
+///     assert!(
+///         write_updated("path/to/file", doc.as_bytes()).unwrap(),
+///         "Doc changes detected, you need to commit the output."
+///     );
+/// # /*
+/// }
+/// # */
+/// ```
+///
+/// # Errors
+/// Reports any file IO errors
+pub fn write_updated<P: AsRef<Path>>(path: P, value: &[u8]) -> io::Result<bool> {
+    update(path.as_ref(), value, &WriteOptions::default())
+        .map(|old| old.map_or(false, |old| old != value))
+}
+
+/// Update file contents if needed and return a unified diff of the changes if it was needed
+///
+/// A missing file is treated as empty. With [`check_only`](WriteOptions::check_only) the diff
+/// is reported but the file is left as is.
+///
+/// ```no_run
+/// # /*
+/// #[test]
+/// fn update_documentation() {
+/// # */
+/// # use ::roff::*;
+///     let doc = "hello world";
+///     let mut options = WriteOptions::default();
+///     options.atomic(true).bless_from_env("BLESS");
+///     if let Some(diff) = write_updated_with("path/to/file", doc.as_bytes(), &options).unwrap() {
+///         panic!("Documentation is outdated, run with BLESS=1 to update:\n{}", diff);
+///     }
+/// # /*
+/// }
+/// # */
+/// ```
+///
+/// # Errors
+/// Reports any file IO errors
+pub fn write_updated_with<P: AsRef<Path>>(
+    path: P,
+    value: &[u8],
+    options: &WriteOptions,
+) -> io::Result<Option<String>> {
+    let path = path.as_ref();
+    Ok(update(path, value, options)?.map(|old| {
+        unified(
+            &String::from_utf8_lossy(&old),
+            &String::from_utf8_lossy(value),
+            &path.display().to_string(),
+        )
+    }))
+}
+
+/// Comment lines delimiting a region updated by [`write_region`]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Markers {
+    /// `<!-- begin: name -->` and `<!-- end: name -->`, for markdown and html files
+    Markdown,
+    /// `.\" begin: name` and `.\" end: name`, for manpages
+    Roff,
+}
+
+impl Markers {
+    fn begin(self, name: &str) -> String {
+        match self {
+            Markers::Markdown => format!("<!-- begin: {} -->", name),
+            Markers::Roff => format!(".\\\" begin: {}", name),
+        }
+    }
+
+    fn end(self, name: &str) -> String {
+        match self {
+            Markers::Markdown => format!("<!-- end: {} -->", name),
+            Markers::Roff => format!(".\\\" end: {}", name),
+        }
+    }
+}
+
+/// Find a line consisting of a single marker and return its byte range
+fn find_marker(path: &Path, text: &str, marker: &str) -> io::Result<(usize, usize)> {
+    let mut found = None;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        if line.trim() == marker {
+            if found.is_some() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("duplicate marker {} in {}", marker, path.display()),
+                ));
+            }
+            found = Some((offset, offset + line.len()));
+        }
+        offset += line.len();
+    }
+    found.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("marker {} not found in {}", marker, path.display()),
+        )
+    })
+}
+
+/// Replace contents of a marked region in a file, with markers on lines of their own
+fn replace_region(
+    path: &Path,
+    text: &str,
+    name: &str,
+    markers: Markers,
+    value: &str,
+) -> io::Result<String> {
+    let (_, start) = find_marker(path, text, &markers.begin(name))?;
+    let (end, _) = find_marker(path, text, &markers.end(name))?;
+    if end < start {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "region {} ends before it begins in {}",
+                name,
+                path.display()
+            ),
+        ));
+    }
+    let mut res = String::with_capacity(text.len() + value.len());
+    res.push_str(&text[..start]);
+    // begin marker could be on the last line without a newline
+    if !res.ends_with('\n') {
+        res.push('\n');
+    }
+    res.push_str(value);
+    if !value.is_empty() && !value.ends_with('\n') {
+        res.push('\n');
+    }
+    res.push_str(&text[end..]);
+    Ok(res)
+}
+
+/// Update contents of a region between two marker lines in an existing file and return if it
+/// was needed
+///
+/// Everything outside of the region is left as is, markers are kept
+///
+/// ```no_run
+/// # use ::roff::*;
+/// // README.md contains lines <!-- begin: usage --> and <!-- end: usage -->
+/// let mut doc = Doc::default();
+/// doc.paragraph([text("Pass "), literal("--help"), text(" for info")]);
+/// write_region("README.md", "usage", Markers::Markdown, &doc.render_to_markdown()).unwrap();
+/// ```
+///
+/// # Errors
+/// Reports any file IO errors, missing or duplicated markers
+pub fn write_region<P: AsRef<Path>>(
+    path: P,
+    name: &str,
+    markers: Markers,
+    value: &str,
+) -> io::Result<bool> {
+    write_region_with(path, name, markers, value, &WriteOptions::default()).map(|d| d.is_some())
+}
+
+/// Update contents of a marked region with custom options and return a unified diff of the
+/// changes if it was needed, see [`write_region`] and [`write_updated_with`]
+///
+/// # Errors
+/// Reports any file IO errors, missing or duplicated markers
+pub fn write_region_with<P: AsRef<Path>>(
+    path: P,
+    name: &str,
+    markers: Markers,
+    value: &str,
+    options: &WriteOptions,
+) -> io::Result<Option<String>> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path)?;
+    let updated = replace_region(path, &text, name, markers, value)?;
+    write_updated_with(path, updated.as_bytes(), options)
+}
+
+/// Name of the file listing generated files, relative to the synchronized directory
+pub const MANIFEST: &str = ".roff-manifest";
+
+/// Changes made by [`sync_dir`], paths are relative to the synchronized directory
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    /// Files that didn't exist before
+    pub added: Vec<PathBuf>,
+    /// Files with updated contents
+    pub changed: Vec<PathBuf>,
+    /// Files generated previously but not anymore
+    pub removed: Vec<PathBuf>,
+}
+
+impl SyncReport {
+    /// Check if the directory was already up to date
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl fmt::Display for SyncReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "Generated files are up to date");
+        }
+        writeln!(f, "Generated files are out of date:")?;
+        for (label, paths) in [
+            ("added", &self.added),
+            ("changed", &self.changed),
+            ("removed", &self.removed),
+        ] {
+            for path in paths {
+                writeln!(f, "    {}: {}", label, path.display())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Make sure path stays inside of the directory it is relative to
+fn check_relative(path: &Path) -> io::Result<()> {
+    let nested = path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    if !nested || path.as_os_str().is_empty() || path == Path::new(MANIFEST) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a valid generated file path", path.display()),
+        ));
+    }
+    Ok(())
+}
+
+/// Manifest contents use forward slashes on all platforms
+fn manifest_line(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Synchronize a directory with a set of generated files
+///
+/// Writes files that changed using [`write_updated`] and removes files listed in the manifest
+/// from the previous run but missing from `files`. Files not mentioned in the manifest are never
+/// touched so the directory can contain anything else. Manifest is stored in the
+/// [`MANIFEST`] file in the same directory.
+///
+/// # Example
+///
+/// Similarly to [`write_updated`] this can be used in a test so CI would fail if checked in files
+/// are outdated.
+/// ```no_run
+/// # /*
+/// #[test]
+/// fn update_documentation() {
+/// # */
+/// # use ::roff::*;
+///     let set = ManualSet::new("foo", Section::General, "do things", text("nothing here"));
+///     let report = sync_dir("doc/man", set.render_to_manpages(&[])).unwrap();
+///     assert!(report.is_empty(), "{}You need to commit the output.", report);
+/// # /*
+/// }
+/// # */
+/// ```
+///
+/// # Errors
+/// Reports any file IO errors, paths must be relative and can't point outside of `dir`
+pub fn sync_dir<D, I, P, B>(dir: D, files: I) -> io::Result<SyncReport>
+where
+    D: AsRef<Path>,
+    I: IntoIterator<Item = (P, B)>,
+    P: AsRef<Path>,
+    B: AsRef<[u8]>,
+{
+    sync_dir_with(dir, files, &WriteOptions::default())
+}
+
+/// Synchronize a directory with a set of generated files using custom options
+///
+/// With [`check_only`](WriteOptions::check_only) nothing is written or removed, the report
+/// lists what would be changed.
+///
+/// # Errors
+/// Reports any file IO errors, paths must be relative and can't point outside of `dir`
+pub fn sync_dir_with<D, I, P, B>(dir: D, files: I, options: &WriteOptions) -> io::Result<SyncReport>
+where
+    D: AsRef<Path>,
+    I: IntoIterator<Item = (P, B)>,
+    P: AsRef<Path>,
+    B: AsRef<[u8]>,
+{
+    let dir = dir.as_ref();
+    let mut generated = BTreeMap::new();
+    for (path, bytes) in files {
+        let path = path.as_ref();
+        check_relative(path)?;
+        generated.insert(manifest_line(path), bytes);
+    }
+
+    let manifest_path = dir.join(MANIFEST);
+    let previous = match std::fs::read_to_string(&manifest_path) {
+        Ok(manifest) => manifest.lines().map(str::to_owned).collect::<Vec<_>>(),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+        Err(err) => return Err(err),
+    };
+
+    let mut report = SyncReport::default();
+    for (name, bytes) in &generated {
+        let path = dir.join(name);
+        let existed = path.exists();
+        if let (Some(parent), false) = (path.parent(), options.check_only) {
+            std::fs::create_dir_all(parent)?;
+        }
+        if update(&path, bytes.as_ref(), options)?.is_some() {
+            if existed {
+                report.changed.push(PathBuf::from(name));
+            } else {
+                report.added.push(PathBuf::from(name));
+            }
+        }
+    }
+
+    for name in previous {
+        if name.is_empty() || generated.contains_key(&name) {
+            continue;
+        }
+        // manifest could be edited by hand
+        let relative = PathBuf::from(&name);
+        check_relative(&relative)?;
+        let path = dir.join(&relative);
+        if options.check_only {
+            if path.exists() {
+                report.removed.push(relative);
+            }
+            continue;
+        }
+        match std::fs::remove_file(&path) {
+            Ok(()) => report.removed.push(relative),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        }
+        // clean up directories left empty, failing to remove a non empty one is fine
+        for parent in path.ancestors().skip(1) {
+            if parent == dir || std::fs::remove_dir(parent).is_err() {
+                break;
+            }
+        }
+    }
+
+    if !options.check_only {
+        std::fs::create_dir_all(dir)?;
+    }
+    let manifest = generated
+        .keys()
+        .map(|name| format!("{}\n", name))
+        .collect::<String>();
+    update(&manifest_path, manifest.as_bytes(), options)?;
+    Ok(report)
+}
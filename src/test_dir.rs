@@ -0,0 +1,34 @@
+//! Temporary directories for tests, shared by unit and integration tests
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static TEST_DIRS: AtomicUsize = AtomicUsize::new(0);
+
+/// An empty directory removed when the value is dropped, even if the test fails
+pub(crate) struct TestDir(PathBuf);
+
+impl TestDir {
+    /// Create a directory with a name unique to the test run and the process
+    pub(crate) fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!(
+            "roff-{}-{}-{}",
+            name,
+            std::process::id(),
+            TEST_DIRS.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
@@ -52,11 +52,11 @@ fn semantic_to_markdown() {
     let doc = doc();
 
     let expected = "\
-# Description
+# <a id=\"description\"></a>Description
 
 <p>Pass <tt><b>--help</b></tt> for info.</p>
 
-# Options
+# <a id=\"options\"></a>Options
 
 <dl>
 <dt><tt><b>-v</b> <b>--verbose</b></tt></dt>
@@ -82,9 +82,7 @@ fn semantic_to_markdown() {
  0: if OK
  1: if not OK</pre>
 
-<p>A few lines
-of text
-.can  be   here</p>";
+<p>A few lines of text .can be here</p>";
 
     assert_eq!(doc.render_to_markdown(), expected);
 }
@@ -0,0 +1,117 @@
+use ::roff::*;
+use std::path::{Path, PathBuf};
+
+#[path = "../src/test_dir.rs"]
+mod test_dir;
+use test_dir::TestDir;
+
+#[test]
+fn sync_dir_reports_changes() {
+    let tmp = TestDir::new("sync");
+    let dir = tmp.path();
+    let paths = |paths: &[PathBuf]| {
+        paths
+            .iter()
+            .map(|p| p.display().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    let report = sync_dir(dir, [("man1/foo.1", "foo"), ("man5/foo.5", "config")]).unwrap();
+    assert_eq!(paths(&report.added), ["man1/foo.1", "man5/foo.5"]);
+    assert!(report.changed.is_empty() && report.removed.is_empty());
+
+    let report = sync_dir(dir, [("man1/foo.1", "foo"), ("man5/foo.5", "config")]).unwrap();
+    assert!(report.is_empty(), "{}", report);
+
+    std::fs::write(dir.join("README"), "not generated").unwrap();
+    let report = sync_dir(dir, [("man1/foo.1", "bar")]).unwrap();
+    assert_eq!(paths(&report.changed), ["man1/foo.1"]);
+    assert_eq!(paths(&report.removed), ["man5/foo.5"]);
+    assert!(!dir.join("man5").exists());
+    assert!(dir.join("README").exists());
+    assert_eq!(
+        report.to_string(),
+        "Generated files are out of date:\n    changed: man1/foo.1\n    removed: man5/foo.5\n"
+    );
+
+    assert!(sync_dir(dir, [(Path::new("../escape"), "")]).is_err());
+}
+
+#[test]
+fn write_updated_empty_file() {
+    let tmp = TestDir::new("empty");
+    let dir = tmp.path();
+    let path = dir.join("foo.1");
+
+    assert!(!write_updated(&path, b"").unwrap());
+    assert!(path.exists());
+    assert!(write_updated(&path, b"foo").unwrap());
+    assert!(!write_updated(&path, b"foo").unwrap());
+}
+
+#[test]
+fn check_only_reports_diff() {
+    let tmp = TestDir::new("check");
+    let dir = tmp.path();
+    let path = dir.join("foo.1");
+    std::fs::write(&path, "a\nb\n").unwrap();
+
+    let mut options = WriteOptions::default();
+    options.check_only(true);
+    let diff = write_updated_with(&path, b"a\nc\n", &options)
+        .unwrap()
+        .unwrap();
+    assert!(diff.ends_with("@@ -1,2 +1,2 @@\n a\n-b\n+c\n"), "{}", diff);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "a\nb\n");
+
+    let report = sync_dir_with(dir, [("man1/foo.1", "")], &options).unwrap();
+    assert_eq!(report.added, [PathBuf::from("man1/foo.1")]);
+    assert!(!dir.join("man1").exists());
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
+        options.check_only(false).atomic(true);
+        assert!(write_updated_with(&path, b"a\nc\n", &options)
+            .unwrap()
+            .is_some());
+        assert!(write_updated_with(&path, b"a\nc\n", &options)
+            .unwrap()
+            .is_none());
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(std::fs::read_dir(dir).unwrap().count(), 1);
+    }
+}
+
+#[test]
+fn marked_region() {
+    let tmp = TestDir::new("region");
+    let dir = tmp.path();
+    let path = dir.join("README.md");
+    std::fs::write(
+        &path,
+        "# Foo\n<!-- begin: usage -->\nold\n<!-- end: usage -->\nThe end\n",
+    )
+    .unwrap();
+
+    assert!(write_region(&path, "usage", Markers::Markdown, "new").unwrap());
+    assert!(!write_region(&path, "usage", Markers::Markdown, "new\n").unwrap());
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "# Foo\n<!-- begin: usage -->\nnew\n<!-- end: usage -->\nThe end\n"
+    );
+
+    let err = write_region(&path, "usage", Markers::Roff, "new").unwrap_err();
+    assert!(
+        err.to_string()
+            .starts_with("marker .\\\" begin: usage not found"),
+        "{}",
+        err
+    );
+
+    std::fs::write(&path, ".\\\" begin: x\n.\\\" end: x\n.\\\" end: x\n").unwrap();
+    let err = write_region(&path, "x", Markers::Roff, ".PP").unwrap_err();
+    assert!(err.to_string().starts_with("duplicate marker"), "{}", err);
+}